@@ -2,10 +2,10 @@ use std::env::args;
 use std::time::Duration;
 
 use anyhow::bail;
-use tracing::{debug, info};
+use tracing::info;
 use tracing_subscriber::filter::LevelFilter;
 
-use bp35c0::Bp35c0;
+use bp35c0::Session;
 use bp35c0::cmd::*;
 
 fn main() -> anyhow::Result<()> {
@@ -24,15 +24,15 @@ fn main() -> anyhow::Result<()> {
     let pwd = &args[2];
 
     let port = serialport::new(tty, 115_200)
-        .timeout(Duration::from_secs(1000))
+        .timeout(Duration::from_secs(1))
         .open()?;
 
-    let mut device = Bp35c0::connect(port)?;
-    let skver::Output { version } = device.version()?;
+    let mut session = Session::connect(port)?;
+    let skver::Output { version } = session.version()?;
 
     info!("Version: {version}");
 
-    let info = device.info()?;
+    let info = session.info()?;
 
     info!("IP Address: {}", info.ip_addr);
     info!("MAC Address: {}", hex::encode(info.addr_64));
@@ -40,34 +40,29 @@ fn main() -> anyhow::Result<()> {
     info!("PAN ID: {0:#x} ({0})", info.pan_id);
     info!("Active Side: {}", info.side);
 
-    device.set_rbid(hex::decode(bid)?.try_into().unwrap())?;
-    device.set_pwd(pwd.as_bytes())?;
+    let mut session = session
+        .authenticate(hex::decode(bid)?.try_into().unwrap(), pwd.as_bytes())
+        .map_err(|f| f.error)?;
 
-    let descs = device.scan_active(true, 0xFFFFFFFF, 0x7, 0x0)?;
+    let descs = session.scan_active(true, 0xFFFFFFFF, 0x7, 0x0)?;
     let desc = match descs.first() {
         Some(d) => d,
         _ => bail!("Coordinator Not Found."),
     };
 
     info!("Coordinator Found: {}", hex::encode(desc.addr));
-
-    let skll64::Output { ip_addr } = device.mac_to_ip_addr(desc.addr)?;
-
-    info!("IP Address: {}", &ip_addr);
-
-    unsafe {
-        device.set_register(sksreg::Register::S02, sksreg::Value::Uint8(desc.channel))?;
-        device.set_register(sksreg::Register::S03, sksreg::Value::Uint16(desc.pan_id))?;
-    }
-
     info!("Joining to Network");
 
-    device.join(ip_addr)?;
+    let mut session = session.join(desc).map_err(|f| f.error)?;
+
+    info!("IP Address: {}", session.coordinator());
 
     loop {
-        unsafe {
-            let payload = device.receive_payload()?;
-            debug!("< {payload:?}")
-        }
+        let udp = session.receive_udp()?;
+        info!(
+            "UDP Received from {}: {}",
+            udp.sender,
+            hex::encode(&udp.data)
+        );
     }
 }
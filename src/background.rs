@@ -0,0 +1,530 @@
+//! 受信をバックグラウンドのスレッドで行うドライバ
+//!
+//! 読み込み側はスレッドが所有し、受信した `EVENT` と `ERXUDP` は購読者全員に配信されます。
+//! コマンドは [`Bp35c0::command`] の中で実行され、その間に受信した行だけがコマンドの応答として呼び出し元に渡されます。
+//! `command` には同期版の [`crate::Bp35c0`] が渡されるので、フラッシュメモリの操作を含む同期版のコマンドを実行できます。
+//! PAN への参加や UDP の送受信は同期版の [`crate::Session`] と同じく状態を遷移させて行い、
+//! PAN に参加した後は [`Bp35c0::session`] の中でセッションを使います。
+//! これにより、イベントの監視とコマンドの実行を別々のスレッドから同時に行えます。
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use tracing::debug;
+
+use crate::cmd::skterm;
+use crate::event::epandesc::EPanDesc;
+use crate::event::erxudp::{ERxUdp, ERXUDP};
+use crate::event::{Event, EVENT};
+use crate::framer::Framer;
+use crate::payload::Payload;
+use crate::driver::is_transient;
+use crate::session::{Authenticated, Connected, Failure, Joined, Transition};
+use crate::{CRLF, Result};
+
+/// コマンド側の読み込みが応答の到着を待つ間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 購読者に配信される通知
+#[derive(Clone, Debug)]
+pub enum Notification {
+    Event(Event),
+    Udp(ERxUdp),
+}
+
+struct Shared {
+    /// コマンドの実行中かどうか
+    active: AtomicBool,
+
+    /// スレッドを停止すべきかどうか
+    stopped: AtomicBool,
+
+    subscribers: Mutex<Vec<Sender<Notification>>>,
+}
+
+impl Shared {
+    fn broadcast(&self, notification: Notification) {
+        // 受信側が破棄された購読者は取り除く
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|s| s.send(notification.clone()).is_ok());
+    }
+
+    fn dispatch(&self, frame: &[u8]) {
+        let payload = Payload::from(frame.to_vec());
+
+        let notification = if payload.name == EVENT {
+            Event::try_from(&payload).map(Notification::Event)
+        } else if payload.name == ERXUDP {
+            ERxUdp::try_from(&payload).map(Notification::Udp)
+        } else {
+            return;
+        };
+
+        match notification {
+            Ok(n) => self.broadcast(n),
+            Err(e) => debug!("Ignoring malformed notification: {e}"),
+        }
+    }
+}
+
+/// コマンド側から見た通信路
+///
+/// 読み込みではバックグラウンドのスレッドから転送された応答を返し、書き込みはそのままポートに書き込みます。
+pub struct Pipe<W> {
+    rx: Receiver<Vec<u8>>,
+    pending: VecDeque<u8>,
+    writer: W,
+}
+
+impl<W> Pipe<W> {
+    /// 転送済みでまだ読み込まれていない行を破棄します。
+    fn drain(&mut self) {
+        self.rx.try_iter().for_each(drop);
+        self.pending.clear();
+    }
+}
+
+impl<W> Read for Pipe<W> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.rx.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => self.pending.extend(line),
+                Err(RecvTimeoutError::Timeout) => return Err(ErrorKind::TimedOut.into()),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            }
+        }
+
+        self.pending.read(buf)
+    }
+}
+
+impl<W> Write for Pipe<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// 受信をバックグラウンドのスレッドで行う BP35C0 ドライバ
+///
+/// スレッド間で共有できるので、あるスレッドでイベントを待ちながら別のスレッドからコマンドを実行できます。
+/// 破棄してもスレッドはすぐには止まらず、次に読み込みがタイムアウトした時点で停止します。
+///
+/// 同期版の [`crate::Session`] と同じく状態 `S` を持ち、PAN への参加や UDP の送受信は [`Joined`] の状態でのみ行えます。
+pub struct Bp35c0<W, S = Connected>
+where
+    W: Write,
+{
+    session: Mutex<PipeSession<S, W>>,
+    handle: Handle,
+}
+
+/// コマンド側の通信路を使うセッション
+type PipeSession<S, W> = crate::Session<S, Pipe<W>>;
+
+/// 破棄されたときに、バックグラウンドのスレッドに停止を要求します。
+struct Handle {
+    shared: Arc<Shared>,
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // スレッドは次の読み込みタイムアウトで停止する
+        self.shared.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Shared {
+    /// コマンドの実行を始め、それまでに転送された行を破棄します。
+    fn begin<W: Write>(&self, device: &mut crate::Bp35c0<Pipe<W>>) {
+        self.active.store(true, Ordering::SeqCst);
+        device.port_mut().drain();
+        device.discard_input();
+    }
+
+    fn end(&self) {
+        self.active.store(false, Ordering::SeqCst);
+    }
+}
+
+impl<W> Bp35c0<W>
+where
+    W: Write,
+{
+    /// 読み込み側 `reader` を所有するスレッドを起動し、書き込み側 `writer` を使って接続します。
+    ///
+    /// シリアルポートであれば、`try_clone` で複製したものを読み込み側として渡します。
+    ///
+    /// スレッドは読み込みがタイムアウトするたびに停止の要求を確認するので、`reader` には読み込みタイムアウトを設定してください
+    /// （[`std::io::ErrorKind::TimedOut`] などを定期的に返す必要があります）。
+    /// タイムアウトしない読み込み側を渡すと、ドライバを破棄してもスレッドが止まらず、接続に失敗した場合はこの関数から戻りません。
+    pub fn spawn<R>(reader: R, writer: W) -> Result<Self>
+    where
+        R: Read + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            active: AtomicBool::new(true),
+            stopped: AtomicBool::new(false),
+            subscribers: Mutex::new(Vec::new()),
+        });
+
+        let (tx, rx) = channel();
+        let thread = {
+            let shared = shared.clone();
+            thread::spawn(move || run(reader, tx, shared))
+        };
+
+        let pipe = Pipe {
+            rx,
+            pending: VecDeque::new(),
+            writer,
+        };
+
+        let device = match crate::Bp35c0::connect(pipe) {
+            Ok(device) => device,
+            Err(e) => {
+                // 読み込み側を手放せるように、スレッドの停止を待ってから返す
+                shared.stopped.store(true, Ordering::SeqCst);
+                _ = thread.join();
+                return Err(e);
+            }
+        };
+
+        shared.active.store(false, Ordering::SeqCst);
+
+        Ok(Self {
+            session: Mutex::new(crate::Session::from(device)),
+            handle: Handle { shared },
+        })
+    }
+
+}
+
+impl<W, S> Bp35c0<W, S>
+where
+    W: Write,
+{
+    /// 以降に受信する `EVENT` と `ERXUDP` を受け取るチャネルを返します。
+    pub fn subscribe(&self) -> Receiver<Notification> {
+        let (tx, rx) = channel();
+        self.handle.shared.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// `f` の中でコマンドを実行します。
+    ///
+    /// コマンドは同時に 1 つずつ実行され、`f` の実行中に受信した行だけが `f` に渡されます。
+    /// `f` が処理しなかった行は、`f` の終了時に破棄されます（イベントであれば購読者には配信済みです）。
+    pub fn command<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut crate::Bp35c0<Pipe<W>>) -> Result<T>,
+    {
+        let mut session = self.session.lock().unwrap();
+        let device = session.device();
+
+        self.handle.shared.begin(device);
+        let result = f(device);
+        self.handle.shared.end();
+
+        result
+    }
+
+    /// セッションの状態を遷移させます。失敗した場合は元の状態のドライバを返します。
+    fn transition<T, U, F>(self, f: F) -> Transition<(Bp35c0<W, T>, U), Self>
+    where
+        F: FnOnce(PipeSession<S, W>) -> Transition<(PipeSession<T, W>, U), PipeSession<S, W>>,
+    {
+        let Self { session, handle } = self;
+        let mut session = session.into_inner().unwrap();
+
+        handle.shared.begin(session.device());
+        let result = f(session);
+        handle.shared.end();
+
+        match result {
+            Ok((session, output)) => Ok((
+                Bp35c0 {
+                    session: Mutex::new(session),
+                    handle,
+                },
+                output,
+            )),
+            Err(failure) => Err(Box::new(Failure {
+                session: Bp35c0 {
+                    session: Mutex::new(failure.session),
+                    handle,
+                },
+                error: failure.error,
+            })),
+        }
+    }
+}
+
+impl<W> Bp35c0<W, Connected>
+where
+    W: Write,
+{
+    /// Route-B の認証 ID とパスワードを設定します。
+    pub fn authenticate(
+        self,
+        rbid: [u8; 16],
+        pwd: &[u8],
+    ) -> Transition<Bp35c0<W, Authenticated>, Self> {
+        self.transition(|s| s.authenticate(rbid, pwd).map(|s| (s, ())))
+            .map(|(d, _)| d)
+    }
+}
+
+impl<W> Bp35c0<W, Authenticated>
+where
+    W: Write,
+{
+    /// スキャンで見つけた PAN に参加します。
+    pub fn join(self, pan: &EPanDesc) -> Transition<Bp35c0<W, Joined>, Self> {
+        self.transition(|s| s.join(pan).map(|s| (s, ())))
+            .map(|(d, _)| d)
+    }
+}
+
+impl<W> Bp35c0<W, Joined>
+where
+    W: Write,
+{
+    /// `f` の中で、PAN に参加しているセッションを使ってコマンドを実行します。
+    ///
+    /// UDP の送受信はこのセッションを通して行います。受信した行の扱いは [`Bp35c0::command`] と同じです。
+    pub fn session<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut crate::Session<Joined, Pipe<W>>) -> Result<T>,
+    {
+        let mut session = self.session.lock().unwrap();
+
+        self.handle.shared.begin(session.device());
+        let result = f(&mut session);
+        self.handle.shared.end();
+
+        result
+    }
+
+    /// PANA セッションを終了し、PAN から離脱します。
+    pub fn terminate(self) -> Transition<(Bp35c0<W, Authenticated>, skterm::Output), Self> {
+        self.transition(|s| s.terminate())
+    }
+}
+
+fn run<R>(reader: R, tx: Sender<Vec<u8>>, shared: Arc<Shared>)
+where
+    R: Read,
+{
+    let mut reader = BufReader::new(reader);
+
+    // バイナリ形式の ERXUDP は DATA に CR や LF を含み得るので、行ではなくフレームに区切る
+    let mut framer = Framer::default();
+
+    while !shared.stopped.load(Ordering::SeqCst) {
+        match reader.fill_buf() {
+            Ok([]) => break,
+            Ok(bytes) => {
+                let len = bytes.len();
+                framer.push(bytes);
+                reader.consume(len);
+            }
+            Err(e) if is_transient(&e) => continue,
+            Err(e) => {
+                debug!("Stopping the reader thread: {e}");
+                break;
+            }
+        }
+
+        while let Some(mut frame) = framer.next_frame() {
+            shared.dispatch(&frame);
+
+            if !shared.active.load(Ordering::SeqCst) {
+                continue;
+            }
+
+            // コマンド側のドライバがもう一度フレームに区切れるように、取り除かれた CRLF を付け直す
+            frame.extend_from_slice(CRLF);
+
+            if tx.send(frame).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Read, Write};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use crate::{Error, FailCode, FlashOperation};
+    use crate::background::{Bp35c0, Notification};
+    use crate::cmd::skterm;
+    use crate::event::{EventBody, UDPSendResult};
+    use crate::mock::MockBp35c0;
+
+    /// 破棄されたことを記録する読み込み側
+    struct Tracked(MockBp35c0, Arc<AtomicBool>);
+
+    impl Read for Tracked {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Drop for Tracked {
+        fn drop(&mut self) {
+            self.1.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// 書き込みに失敗する書き込み側
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let mock = MockBp35c0::new();
+        mock.set_version("1.2.10");
+
+        let device = Bp35c0::spawn(mock.clone(), mock.clone()).unwrap();
+        let events = device.subscribe();
+
+        mock.push_line(b"EVENT 29 FE80:0000:0000:0000:021D:1290:1234:5678 0");
+        mock.push_line(
+            b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:0000:0001 0E1A 0E1A 001D129012345678 1 0 0002 ABCD",
+        );
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            Notification::Event(e) if matches!(e.body, EventBody::PanaTimedOut),
+        ));
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            Notification::Udp(u) if u.data == [0xAB, 0xCD],
+        ));
+
+        // イベントの配信中もコマンドを実行できる
+        let version = device.command(|d| d.version()).unwrap().version;
+        assert_eq!("1.2.10", version);
+    }
+
+    #[test]
+    fn test_session() {
+        let mock = MockBp35c0::with_test_pan();
+
+        let device = Bp35c0::spawn(mock.clone(), mock.clone()).unwrap();
+        let pan = device
+            .command(|d| d.scan_active(true, 0xFFFFFFFF, 6, 0))
+            .unwrap()
+            .remove(0);
+
+        let device = device.authenticate([0x30; 16], b"PASSWORD").unwrap();
+
+        // 参加に失敗しても、認証情報を設定した状態のドライバでやり直せる
+        mock.push_join_result(false);
+        let failure = device.join(&pan).err().unwrap();
+        assert!(matches!(failure.error, Error::JoinFailed));
+
+        let device = failure.session.join(&pan).unwrap();
+        let coordinator = device.session(|s| Ok(s.coordinator())).unwrap();
+        assert!(matches!(
+            device.session(|s| s.send_udp(0x0E1A, b"a")),
+            Ok(UDPSendResult::Success)
+        ));
+        assert_eq!(vec![(coordinator, 0x0E1A, b"a".to_vec())], mock.datagrams());
+
+        let (_, output) = device.terminate().unwrap();
+        assert_eq!(skterm::Output::Terminated, output);
+    }
+
+    #[test]
+    fn test_flash_commands() {
+        let mock = MockBp35c0::new();
+        let device = Bp35c0::spawn(mock.clone(), mock.clone()).unwrap();
+
+        device.command(|d| d.save_config()).unwrap();
+        assert_eq!(Some(b"21".to_vec()), mock.saved_register(b"S02"));
+        device.command(|d| d.load_config()).unwrap();
+
+        mock.fail_next(b"SKERASE", 10);
+        assert!(matches!(
+            device.command(|d| d.erase_config()),
+            Err(Error::Flash {
+                operation: FlashOperation::Erase,
+                code: FailCode::CommandFailed,
+            })
+        ));
+        device.command(|d| d.erase_config()).unwrap();
+        assert_eq!(None, mock.saved_register(b"S02"));
+    }
+
+    #[test]
+    fn test_binary_udp() {
+        const ERXUDP: &[u8] = b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:1234:9ABC 0E1A 0E1A 001D129012345678 1 0 0004 \r\n a";
+
+        let mock = MockBp35c0::new();
+        let device = Bp35c0::spawn(mock.clone(), mock.clone()).unwrap();
+        let events = device.subscribe();
+
+        // DATA に CRLF を含むバイナリ形式の ERXUDP も 1 件として配信し、後続の行を取り違えない
+        mock.push_line(ERXUDP);
+        mock.push_line(b"EVENT 29 FE80:0000:0000:0000:021D:1290:1234:5678 0");
+
+        let timeout = Duration::from_secs(5);
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            Notification::Udp(u) if u.data == b"\r\n a",
+        ));
+        assert!(matches!(
+            events.recv_timeout(timeout).unwrap(),
+            Notification::Event(e) if matches!(e.body, EventBody::PanaTimedOut),
+        ));
+
+        // コマンドの実行中に受信した場合も同じ
+        let udp = device
+            .command(|d| {
+                mock.push_line(ERXUDP);
+                d.receive_udp()
+            })
+            .unwrap();
+        assert_eq!(b"\r\n a", udp.data.as_slice());
+    }
+
+    #[test]
+    fn test_spawn_failure() {
+        let dropped = Arc::new(AtomicBool::new(false));
+        let reader = Tracked(MockBp35c0::new(), dropped.clone());
+
+        // 接続に失敗した場合は、スレッドが停止して読み込み側を破棄してから返る
+        let result = Bp35c0::spawn(reader, Broken);
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == ErrorKind::BrokenPipe));
+        assert!(dropped.load(Ordering::SeqCst));
+    }
+}
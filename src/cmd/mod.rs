@@ -1,21 +1,45 @@
+use core::time::Duration;
+
+use crate::{Error, FlashOperation, Result};
 use crate::payload::Payload;
 
+pub mod skerase;
 pub mod skinfo;
 pub mod skjoin;
 pub mod skll64;
+pub mod skload;
+pub mod skping;
+pub mod skrejoin;
 pub mod skreset;
+pub mod sksave;
 pub mod skscan;
+pub mod sksendto;
 pub mod sksetpwd;
 pub mod sksetrbid;
 pub mod sksreg;
+pub mod skterm;
 pub mod skver;
 
+/// フラッシュメモリを操作するコマンド (SKSAVE, SKLOAD, SKERASE) の応答を待つ既定の時間
+///
+/// リファレンスには応答までの時間が記載されていないため、フラッシュメモリの操作にかかる時間を見込んで余裕を持たせた値です。
+/// リファレンスに由来する値ではありません。
+pub const FLASH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// フラッシュメモリの操作に対してモジュールが返した `FAIL ERxx` を、操作の種類を付けた [`Error::Flash`] に変換します。
+pub(crate) fn flash_error(operation: FlashOperation) -> impl Fn(Error) -> Error {
+    move |e| match e {
+        Error::Fail(code) => Error::Flash { operation, code },
+        e => e,
+    }
+}
+
 pub trait Encode {
     fn encode(&self) -> Payload;
 }
 
 pub trait Decode: Sized {
-    fn decode(payload: &Payload) -> Self;
+    fn decode(payload: &Payload) -> Result<Self>;
 }
 
 pub trait Response: Decode {
@@ -0,0 +1,32 @@
+use alloc::vec;
+
+use crate::{FlashOperation, Result};
+use crate::cmd::{flash_error, Encode, FLASH_TIMEOUT};
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+
+const SKERASE: &[u8] = b"SKERASE";
+
+#[derive(Clone, Debug)]
+pub struct Input {}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKERASE.into(),
+            args: vec![],
+            data: None,
+        }
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn erase_config(&mut self) -> Result<()> {
+        let deadline = self.io.deadline(FLASH_TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_ok_until(deadline)
+            .await
+            .map_err(flash_error(FlashOperation::Erase))
+    }
+}
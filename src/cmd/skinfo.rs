@@ -1,15 +1,18 @@
-use std::net::Ipv6Addr;
-use std::str::FromStr;
+use alloc::vec;
+use core::net::Ipv6Addr;
+use core::time::Duration;
 
-use byteorder::{BigEndian, ByteOrder};
-
-use crate::{Bp35c0, Result};
+use crate::Result;
 use crate::cmd::{Decode, Encode, Response};
 use crate::payload::Payload;
-use crate::utils::parse_hex_bytes;
+use crate::protocol::{Io, Protocol};
+use crate::utils::{arg, parse_flag, parse_hex_array, parse_hex_u16, parse_hex_u8, parse_ipv6};
 
 const SKINFO: &[u8] = b"SKINFO";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {}
 
@@ -18,6 +21,7 @@ impl Encode for Input {
         Payload {
             name: SKINFO.into(),
             args: vec![],
+            data: None,
         }
     }
 }
@@ -32,17 +36,14 @@ pub struct Output {
 }
 
 impl Decode for Output {
-    fn decode(payload: &Payload) -> Self {
-        Self {
-            ip_addr: Ipv6Addr::from_str(&String::from_utf8_lossy(&payload.args[0])).unwrap(),
-            addr_64: <[u8; 8]>::try_from(parse_hex_bytes(&payload.args[1])).unwrap(),
-            channel: parse_hex_bytes(&payload.args[2])[0],
-            pan_id: BigEndian::read_u16(&parse_hex_bytes(&payload.args[3])),
-            side: match payload.args[4][0] {
-                b'1' => 1,
-                _ => 0,
-            },
-        }
+    fn decode(payload: &Payload) -> Result<Self> {
+        Ok(Self {
+            ip_addr: parse_ipv6("ip_addr", arg(payload, 0, "ip_addr")?)?,
+            addr_64: parse_hex_array("addr_64", arg(payload, 1, "addr_64")?)?,
+            channel: parse_hex_u8("channel", arg(payload, 2, "channel")?)?,
+            pan_id: parse_hex_u16("pan_id", arg(payload, 3, "pan_id")?)?,
+            side: parse_flag("side", arg(payload, 4, "side")?)? as u16,
+        })
     }
 }
 
@@ -50,11 +51,11 @@ impl Response for Output {
     const NAME: &'static [u8] = b"EINFO";
 }
 
-impl Bp35c0 {
-    pub fn info(&mut self) -> Result<Output> {
-        unsafe {
-            self.send(&Input {})?;
-            self.wait_for_response::<Output>()
-        }
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn info(&mut self) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_response_until::<Output>(deadline).await
     }
 }
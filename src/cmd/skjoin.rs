@@ -1,15 +1,19 @@
-use std::net::Ipv6Addr;
+use alloc::vec;
+use core::net::Ipv6Addr;
+use core::time::Duration;
 
-use bstr::BString;
-
-use crate::{Bp35c0, Result};
+use crate::Result;
 use crate::cmd::Encode;
 use crate::event::EventBody;
 use crate::payload::Payload;
-use crate::utils::u16_to_hex_bytes;
+use crate::protocol::{Io, Protocol};
+use crate::utils::ipv6_to_hex_bytes;
 
 const SKJOIN: &[u8] = b"SKJOIN";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug)]
 pub struct Input {
     pub ip_addr: Ipv6Addr,
@@ -19,27 +23,45 @@ impl Encode for Input {
     fn encode(&self) -> Payload {
         Payload {
             name: SKJOIN.into(),
-            args: vec![bstr::join(
-                b":",
-                self.ip_addr
-                    .segments()
-                    .map(u16_to_hex_bytes)
-                    .map(BString::from),
-            )],
+            args: vec![ipv6_to_hex_bytes(&self.ip_addr)],
+            data: None,
         }
     }
 }
 
-impl Bp35c0 {
-    pub unsafe fn join_nowait(&mut self, ip_addr: Ipv6Addr) -> Result<()> {
-        self.send(&Input { ip_addr })
+/// PANA 認証の結果
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Output {
+    /// 接続に成功した (EVENT 25)
+    Connected,
+
+    /// 接続に失敗した (EVENT 24)
+    Failed,
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn join_nowait(&mut self, ip_addr: Ipv6Addr) -> Result<()> {
+        self.send(&Input { ip_addr }).await
     }
 
-    pub fn join(&mut self, ip_addr: Ipv6Addr) -> Result<()> {
-        unsafe {
-            self.join_nowait(ip_addr)?;
-            self.wait_for_event(|e| matches!(e.body, EventBody::PanaConnected))?;
-            self.wait_for_ok()
-        }
+    pub(crate) async fn join(&mut self, ip_addr: Ipv6Addr) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.join_nowait(ip_addr).await?;
+        self.wait_for_ok_until(deadline).await?;
+        self.wait_for_pana(deadline).await
+    }
+
+    pub(crate) async fn wait_for_pana(&mut self, deadline: Option<I::Instant>) -> Result<Output> {
+        let event = self
+            .wait_for_event_until(deadline, |e| {
+                matches!(e.body, EventBody::PanaConnected | EventBody::PanaError)
+            })
+            .await?;
+
+        Ok(match event.body {
+            EventBody::PanaConnected => Output::Connected,
+            _ => Output::Failed,
+        })
     }
 }
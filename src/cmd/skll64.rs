@@ -1,13 +1,20 @@
-use std::net::Ipv6Addr;
-use std::str::FromStr;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::Ipv6Addr;
+use core::time::Duration;
 
-use crate::{Bp35c0, Result};
+use crate::{Error, FailCode, Result};
 use crate::cmd::Encode;
+use crate::error::FAIL;
 use crate::payload::Payload;
-use crate::utils::to_hex_bytes;
+use crate::protocol::{Io, Protocol};
+use crate::utils::{parse_ipv6, to_hex_bytes};
 
 const SKLL64: &[u8] = b"SKLL64";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {
     pub addr_64: [u8; 8],
@@ -18,6 +25,7 @@ impl Encode for Input {
         Payload {
             name: SKLL64.into(),
             args: vec![to_hex_bytes(&self.addr_64)],
+            data: None,
         }
     }
 }
@@ -27,19 +35,33 @@ pub struct Output {
     pub ip_addr: Ipv6Addr,
 }
 
-impl From<&[u8]> for Output {
-    fn from(value: &[u8]) -> Self {
-        Self {
-            ip_addr: Ipv6Addr::from_str(&String::from_utf8_lossy(value)).unwrap(),
+impl Output {
+    /// SKLL64 の応答は名前の無い 1 行なので、`FAIL ERxx` でないことを確かめてから変換します。
+    pub(crate) fn decode_line(line: Vec<u8>) -> Result<Self> {
+        if line.starts_with(FAIL) {
+            return Err(Error::Fail(FailCode::from(&Payload::from(line))));
         }
+
+        Self::try_from(line.as_slice())
     }
 }
 
-impl Bp35c0 {
-    pub fn mac_to_ip_addr(&mut self, addr_64: [u8; 8]) -> Result<Output> {
-        unsafe {
-            self.send(&Input { addr_64 })?;
-            Ok(self.receive_until_crlf()?.as_slice().into())
-        }
+impl TryFrom<&[u8]> for Output {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> Result<Self> {
+        Ok(Self {
+            ip_addr: parse_ipv6("ip_addr", value)?,
+        })
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn mac_to_ip_addr(&mut self, addr_64: [u8; 8]) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input { addr_64 }).await?;
+
+        Output::decode_line(self.receive_frame_until(deadline).await?)
     }
 }
@@ -0,0 +1,40 @@
+use alloc::vec;
+
+use crate::{FlashOperation, Result};
+use crate::cmd::{flash_error, Encode, FLASH_TIMEOUT};
+use crate::cmd::sksreg::{Register, Value};
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+
+const SKLOAD: &[u8] = b"SKLOAD";
+
+#[derive(Clone, Debug)]
+pub struct Input {}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKLOAD.into(),
+            args: vec![],
+            data: None,
+        }
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn load_config(&mut self) -> Result<()> {
+        let deadline = self.io.deadline(FLASH_TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_ok_until(deadline)
+            .await
+            .map_err(flash_error(FlashOperation::Load))?;
+
+        // 読み込んだ設定で SFE が変わり得るので、エコーバックがあるものとして読み出してから合わせる
+        self.echo.set_enabled(true);
+        let enabled = self.read_register(Register::SFE).await?.value == Value::Bool(true);
+        self.echo.set_enabled(enabled);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::vec;
+use core::net::Ipv6Addr;
+use core::time::Duration;
+
+#[cfg(feature = "std")]
+use tracing::warn;
+
+#[cfg(feature = "std")]
+use crate::{Result, WaitMap};
+use crate::cmd::Encode;
+#[cfg(feature = "std")]
+use crate::event::epong::{EPong, EPONG};
+use crate::payload::Payload;
+#[cfg(feature = "std")]
+use crate::protocol::{Io, Protocol};
+use crate::utils::{ipv6_to_hex_bytes, itoa};
+
+const SKPING: &[u8] = b"SKPING";
+
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct Input {
+    pub ip_addr: Ipv6Addr,
+    pub side: u8,
+}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKPING.into(),
+            args: vec![
+                ipv6_to_hex_bytes(&self.ip_addr),
+                itoa(self.side)[1..].into(),
+            ],
+            data: None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<I: Io> Protocol<I> {
+    /// Echo reply (EPONG) を受信するまで待機します。応答までの時間はドライバの側で計ります。
+    pub(crate) async fn ping(&mut self, ip_addr: Ipv6Addr, side: u8) -> Result<()> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input { ip_addr, side }).await?;
+        self.wait_for_ok_until(deadline).await?;
+        self.wait_map_until(deadline, |p| {
+            if p.name == EPONG {
+                match EPong::try_from(&p) {
+                    Ok(pong) if pong.sender == ip_addr => return WaitMap::Finish(()),
+                    Err(e) => {
+                        warn!("Ignoring malformed EPONG: {e}");
+                        return WaitMap::Consume;
+                    }
+                    _ => {}
+                }
+            }
+
+            WaitMap::Continue(p)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::{Bp35c0, Split};
+    #[cfg(feature = "std")]
+    use crate::event::EventBody;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_ignore_echo_request() {
+        // SKRESET, SKSREG, SKPING への応答（EPONG より先に他の端末からの Echo request が届く場合）
+        const RX: &[u8] = b"OK\r\nOK\r\nOK\r\nEVENT 05 FE80:0000:0000:0000:021D:1290:1234:5678 0\r\nEPONG FE80:0000:0000:0000:021D:1290:1234:5678 0\r\n";
+
+        let mut device = Bp35c0::connect(Split::new(RX, Vec::new())).unwrap();
+        let ip_addr = "fe80::21d:1290:1234:5678".parse().unwrap();
+        assert!(device.ping(ip_addr, 0).is_ok());
+
+        // EVENT 05 は Echo reply ではないので、読み捨てずに残しておく
+        let event = device.wait_for_event(|_| true).unwrap();
+        assert!(matches!(event.body, EventBody::EchoRequest));
+    }
+}
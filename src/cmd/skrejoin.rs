@@ -0,0 +1,36 @@
+use alloc::vec;
+use core::time::Duration;
+
+use crate::Result;
+use crate::cmd::Encode;
+use crate::cmd::skjoin::Output;
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+
+const SKREJOIN: &[u8] = b"SKREJOIN";
+
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct Input {}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKREJOIN.into(),
+            args: vec![],
+            data: None,
+        }
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn rejoin(&mut self) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_ok_until(deadline).await?;
+        self.wait_for_pana(deadline).await
+    }
+}
@@ -1,9 +1,16 @@
-use crate::{Bp35c0, Result};
+use alloc::vec;
+use core::time::Duration;
+
+use crate::Result;
 use crate::cmd::Encode;
 use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
 
 const SKRESET: &[u8] = b"SKRESET";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {}
 
@@ -12,13 +19,20 @@ impl Encode for Input {
         Payload {
             name: SKRESET.into(),
             args: vec![],
+            data: None,
         }
     }
 }
 
-impl Bp35c0 {
-    pub unsafe fn reset(&mut self) -> Result<()> {
-        self.send(&Input {})?;
-        self.wait_for_ok()
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn reset(&mut self) -> Result<()> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_ok_until(deadline).await?;
+
+        // リセットするとエコーバックは既定の有効な状態に戻る
+        self.echo.set_enabled(true);
+        Ok(())
     }
 }
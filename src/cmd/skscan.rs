@@ -1,12 +1,43 @@
-use crate::{Bp35c0, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use tracing::warn;
+
+use crate::{Error, Result};
 use crate::cmd::Encode;
-use crate::event::{Event, EVENT, EventBody, RawEvent};
+use crate::event::{Event, EVENT, EventBody};
+use crate::event::eedscan::{EnergyLevel, EEDSCAN};
 use crate::event::epandesc::{EPanDesc, EPANDESC};
 use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
 use crate::utils::{itoa, u32_to_hex_bytes};
 
 const SKSCAN: &[u8] = b"SKSCAN";
 
+/// DURATION に指定できる最大の値
+pub const MAX_DURATION: u8 = 14;
+
+/// スキャンの完了を待つ既定の時間を求めます。
+/// 1 チャネルあたりのスキャン時間はおよそ 0.01 秒 * (2^DURATION + 1) となるため、これに余裕を持たせた値を返します。
+/// 計算が [`Duration`] に収まらない場合は [`Duration::MAX`] を返します。
+pub fn timeout(channel_mask: u32, duration: u8) -> Duration {
+    2u32.checked_pow(duration as u32)
+        .and_then(|n| n.checked_add(1))
+        .and_then(|n| Duration::from_millis(10).checked_mul(n))
+        .and_then(|d| d.checked_mul(channel_mask.count_ones()))
+        .map_or(Duration::MAX, |d| d.saturating_add(Duration::from_secs(10)))
+}
+
+/// DURATION が [`MAX_DURATION`] を超えていれば [`Error::OutOfRange`] を返します。
+fn validate_duration(duration: u8) -> Result<()> {
+    if duration > MAX_DURATION {
+        return Err(Error::OutOfRange { field: "duration" });
+    }
+
+    Ok(())
+}
+
 #[repr(u8)]
 #[derive(Copy, Clone, Debug)]
 pub enum Mode {
@@ -33,18 +64,23 @@ impl Encode for Input {
                 itoa(self.duration).into(),
                 itoa(self.side)[1..].into(),
             ],
+            data: None,
         }
     }
 }
 
-impl Bp35c0 {
-    pub unsafe fn scan_active_nowait(
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn scan_active_nowait(
         &mut self,
         ie: bool,
         channel_mask: u32,
         duration: u8,
         side: u8,
     ) -> Result<()> {
+        validate_duration(duration)?;
+
+        let deadline = self.io.deadline(timeout(channel_mask, duration));
+
         self.send(&Input {
             mode: if ie {
                 Mode::Active
@@ -54,44 +90,172 @@ impl Bp35c0 {
             channel_mask,
             duration,
             side,
-        })?;
-        self.wait_for_ok()
+        })
+        .await?;
+        self.wait_for_ok_until(deadline).await
     }
 
-    pub fn scan_active(
+    pub(crate) async fn scan_active(
         &mut self,
         ie: bool,
         channel_mask: u32,
         duration: u8,
         side: u8,
     ) -> Result<Vec<EPanDesc>> {
+        let deadline = self.io.deadline(timeout(channel_mask, duration));
         let mut descs = Vec::<EPanDesc>::new();
         let mut buf = Vec::<Payload>::new();
 
-        unsafe {
-            self.scan_active_nowait(ie, channel_mask, duration, side)?;
+        self.scan_active_nowait(ie, channel_mask, duration, side)
+            .await?;
 
-            loop {
-                let payload = self.receive_payload()?;
+        let result = loop {
+            let is_finished = |b: &EventBody| matches!(b, EventBody::ActiveScanFinished);
 
-                if payload.name == EPANDESC {
-                    descs.push(self.receive_epandesc()?);
-                    continue;
+            match self.receive_scan_result(deadline, is_finished).await {
+                Ok(Some(p)) if p.name == EPANDESC => {
+                    match self.receive_epandesc_until(deadline).await {
+                        Ok(desc) => descs.push(desc),
+                        Err(e) => break Err(e),
+                    }
                 }
+                Ok(Some(p)) => buf.push(p),
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        // 処理しなかったペイロードは、中断された場合も含めて後続の待機のためにバッファに残す
+        buf.into_iter().for_each(|p| self.buf.push_back(p));
 
-                if payload.name == EVENT {
-                    let event = Event::from(&RawEvent::from(&payload));
-                    if let EventBody::ActiveScanFinished = event.body {
-                        break;
+        result.map(|_| descs)
+    }
+
+    pub(crate) async fn scan_energy_nowait(
+        &mut self,
+        channel_mask: u32,
+        duration: u8,
+        side: u8,
+    ) -> Result<()> {
+        validate_duration(duration)?;
+
+        let deadline = self.io.deadline(timeout(channel_mask, duration));
+
+        self.send(&Input {
+            mode: Mode::ED,
+            channel_mask,
+            duration,
+            side,
+        })
+        .await?;
+        self.wait_for_ok_until(deadline).await
+    }
+
+    pub(crate) async fn scan_energy(
+        &mut self,
+        channel_mask: u32,
+        duration: u8,
+        side: u8,
+    ) -> Result<Vec<EnergyLevel>> {
+        let deadline = self.io.deadline(timeout(channel_mask, duration));
+        let mut levels = Vec::<EnergyLevel>::new();
+        let mut buf = Vec::<Payload>::new();
+
+        self.scan_energy_nowait(channel_mask, duration, side)
+            .await?;
+
+        // EVENT 1F が EEDSCAN より先に届くこともあるので、両方を受信するまで待つ
+        let mut received = false;
+        let mut finished = false;
+
+        let result = loop {
+            if received && finished {
+                break Ok(());
+            }
+
+            let is_finished = |b: &EventBody| matches!(b, EventBody::EDScanFinished);
+
+            match self.receive_scan_result(deadline, is_finished).await {
+                Ok(Some(p)) if p.name == EEDSCAN => {
+                    match self.receive_eedscan_until(deadline, &p).await {
+                        Ok(l) => {
+                            levels.extend(l);
+                            received = true;
+                        }
+                        Err(e) => break Err(e),
                     }
                 }
+                Ok(Some(p)) => buf.push(p),
+                Ok(None) => finished = true,
+                Err(e) => break Err(e),
+            }
+        };
+
+        buf.into_iter().for_each(|p| self.buf.push_back(p));
+
+        result.map(|_| levels)
+    }
 
-                buf.push(payload);
+    /// スキャン中に受信したペイロードを 1 件読み込みます。
+    /// `finished` を満たす完了イベントであれば `None` を返し、不正な EVENT は読み捨てます。
+    async fn receive_scan_result(
+        &mut self,
+        deadline: Option<I::Instant>,
+        finished: fn(&EventBody) -> bool,
+    ) -> Result<Option<Payload>> {
+        loop {
+            let payload = self.receive_payload_until(deadline).await?;
+            if payload.name != EVENT {
+                return Ok(Some(payload));
+            }
+
+            match Event::try_from(&payload) {
+                Ok(event) if finished(&event.body) => return Ok(None),
+                Ok(_) => return Ok(Some(payload)),
+                Err(e) => warn!("Ignoring malformed EVENT: {e}"),
             }
         }
+    }
+}
 
-        buf.into_iter().for_each(|p| self.buf.push_back(p));
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    #[cfg(feature = "std")]
+    use crate::{Bp35c0, Error};
+    use crate::cmd::skscan::{timeout, MAX_DURATION};
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(Duration::from_millis(10_020), timeout(0x1, 0));
+        assert_eq!(Duration::from_millis(173_850), timeout(0x1, MAX_DURATION));
+        assert_eq!(Duration::MAX, timeout(0xFFFF_FFFF, 32));
+        assert_eq!(Duration::MAX, timeout(0xFFFF_FFFF, u8::MAX));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_duration_out_of_range() {
+        let mock = MockBp35c0::new();
+        let mut device = Bp35c0::connect(mock.clone()).unwrap();
+
+        // 値域を超える DURATION は送信せずにエラーにする
+        let duration = MAX_DURATION + 1;
+        assert!(matches!(
+            device.scan_active(true, 0xFFFFFFFF, duration, 0),
+            Err(Error::OutOfRange { field: "duration" })
+        ));
+        assert!(matches!(
+            device.scan_energy(0xFFFFFFFF, duration, 0),
+            Err(Error::OutOfRange { field: "duration" })
+        ));
+        assert!(!mock.received().iter().any(|c| c.starts_with(b"SKSCAN")));
 
-        Ok(descs)
+        assert!(device
+            .scan_active(true, 0xFFFFFFFF, MAX_DURATION, 0)
+            .is_ok());
     }
 }
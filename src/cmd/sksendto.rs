@@ -0,0 +1,205 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::Ipv6Addr;
+use core::time::Duration;
+
+use crate::{Error, Result};
+use crate::cmd::Encode;
+use crate::event::{EventBody, UDPSendResult};
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+use crate::utils::{ipv6_to_hex_bytes, itoa, u16_to_hex_bytes};
+
+const SKSENDTO: &[u8] = b"SKSENDTO";
+
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// UDP ハンドルの最大値（ハンドルは 1 から始まる）
+pub const MAX_HANDLE: u8 = 6;
+
+/// 1 回の送信で送れるデータの最大の長さ (DATALEN = 0x04D0)
+pub const MAX_DATA_LEN: usize = 0x04D0;
+
+/// 送信データの暗号化フラグ
+#[repr(u8)]
+#[derive(Copy, Clone, Debug)]
+pub enum Security {
+    /// 平文で送信
+    Plain = 0,
+
+    /// 暗号化して送信（セキュリティが無効な相手には送信されません）
+    Encrypted = 1,
+
+    /// セキュリティが有効な相手には暗号化し、そうでなければ平文で送信
+    Auto = 2,
+}
+
+#[derive(Clone, Debug)]
+pub struct Input {
+    pub handle: u8,
+    pub ip_addr: Ipv6Addr,
+    pub port: u16,
+    pub security: Security,
+    pub side: u8,
+    pub data: Vec<u8>,
+}
+
+impl Input {
+    /// ハンドルや MAC 面、データの長さが SKSENDTO で送信できる範囲に収まっているかを確かめます。
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_HANDLE).contains(&self.handle) {
+            return Err(Error::OutOfRange { field: "handle" });
+        }
+
+        if self.side > 1 {
+            return Err(Error::OutOfRange { field: "side" });
+        }
+
+        if self.data.len() > MAX_DATA_LEN {
+            return Err(Error::OutOfRange { field: "data" });
+        }
+
+        Ok(())
+    }
+}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKSENDTO.into(),
+            args: vec![
+                itoa(self.handle)[1..].into(),
+                ipv6_to_hex_bytes(&self.ip_addr),
+                u16_to_hex_bytes(self.port).into(),
+                itoa(self.security as u8)[1..].into(),
+                itoa(self.side)[1..].into(),
+                u16_to_hex_bytes(self.data.len() as u16).into(),
+            ],
+            data: Some(self.data.clone()),
+        }
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn send_to(
+        &mut self,
+        handle: u8,
+        ip_addr: Ipv6Addr,
+        port: u16,
+        security: Security,
+        side: u8,
+        data: &[u8],
+    ) -> Result<UDPSendResult> {
+        let input = Input {
+            handle,
+            ip_addr,
+            port,
+            security,
+            side,
+            data: data.to_vec(),
+        };
+        input.validate()?;
+
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&input).await?;
+
+        // EVENT 21 は OK より先に届くことがあるが、OK を待つ間に受信した EVENT はバッファに残るので
+        // 先に OK か FAIL を確認しておく（FAIL の場合は EVENT 21 が届かない）
+        self.wait_for_ok_until(deadline).await?;
+        let event = self
+            .wait_for_event_until(deadline, |e| {
+                matches!(e.body, EventBody::UDPSendFinished { .. })
+            })
+            .await?;
+
+        match event.body {
+            EventBody::UDPSendFinished { result } => Ok(result),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::Ipv6Addr;
+
+    #[cfg(feature = "std")]
+    use crate::{Bp35c0, Split};
+    use crate::Error;
+    use crate::cmd::Encode;
+    use crate::cmd::sksendto::{Input, Security, MAX_DATA_LEN, MAX_HANDLE};
+
+    #[test]
+    fn test_encode() {
+        let input = Input {
+            handle: 1,
+            ip_addr: Ipv6Addr::new(0xFE80, 0, 0, 0, 0x1234, 0x5678, 0x9ABC, 0xDEF0),
+            port: 0x0E1A,
+            security: Security::Encrypted,
+            side: 0,
+            data: b"a b\r\n".to_vec(),
+        };
+
+        assert_eq!(
+            b"SKSENDTO 1 FE80:0000:0000:0000:1234:5678:9ABC:DEF0 0E1A 1 0 0005 a b\r\n".to_vec(),
+            Vec::<u8>::from(&input.encode()),
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = Input {
+            handle: 1,
+            ip_addr: Ipv6Addr::LOCALHOST,
+            port: 0x0E1A,
+            security: Security::Encrypted,
+            side: 0,
+            data: vec![0; MAX_DATA_LEN],
+        };
+        let with = |f: fn(&mut Input)| {
+            let mut input = valid.clone();
+            f(&mut input);
+            input
+        };
+
+        assert!(valid.validate().is_ok());
+        assert!(with(|i| i.handle = MAX_HANDLE).validate().is_ok());
+        assert!(with(|i| i.side = 1).validate().is_ok());
+
+        for (input, field) in [
+            (with(|i| i.handle = 0), "handle"),
+            (with(|i| i.handle = 0x12), "handle"),
+            (with(|i| i.side = 2), "side"),
+            (with(|i| i.data = vec![0; MAX_DATA_LEN + 1]), "data"),
+            (with(|i| i.data = vec![0; 0x10000]), "data"),
+        ] {
+            assert!(
+                matches!(input.validate(), Err(Error::OutOfRange { field: f }) if f == field),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_send_to_out_of_range() {
+        // SKRESET, SKSREG への応答
+        let port = Split::new(b"OK\r\nOK\r\n".as_slice(), Vec::new());
+        let mut device = Bp35c0::connect(port).unwrap();
+
+        // 不正な引数は何も送信せずにエラーにする
+        let sent = device.port_mut().writer.len();
+        let result = device.send_to(
+            0x12,
+            Ipv6Addr::LOCALHOST,
+            0x0E1A,
+            Security::Encrypted,
+            0,
+            b"a",
+        );
+        assert!(matches!(result, Err(Error::OutOfRange { field: "handle" })));
+        assert_eq!(sent, device.port_mut().writer.len());
+    }
+}
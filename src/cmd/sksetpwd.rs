@@ -1,29 +1,39 @@
-use crate::{Bp35c0, Result};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::Result;
 use crate::cmd::Encode;
 use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
 use crate::utils::itoa;
 
 const SKSETPWD: &[u8] = b"SKSETPWD";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {
-    pwd: Vec<u8>,
+    pub pwd: Vec<u8>,
 }
 
 impl Encode for Input {
     fn encode(&self) -> Payload {
         Payload {
             name: SKSETPWD.into(),
-            args: vec![itoa(self.pwd.len() as u8).into(), self.pwd.clone()],
+            args: vec![itoa(self.pwd.len() as u8).into()],
+            // パスワードは空白を含み得るので、分割されないように末尾のデータとして送る
+            data: Some(self.pwd.clone()),
         }
     }
 }
 
-impl Bp35c0 {
-    pub fn set_pwd(&mut self, pwd: &[u8]) -> Result<()> {
-        unsafe {
-            self.send(&Input { pwd: pwd.to_vec() })?;
-            self.wait_for_ok()
-        }
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn set_pwd(&mut self, pwd: &[u8]) -> Result<()> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input { pwd: pwd.to_vec() }).await?;
+        self.wait_for_ok_until(deadline).await
     }
 }
@@ -1,13 +1,20 @@
-use crate::{Bp35c0, Result};
+use alloc::vec;
+use core::time::Duration;
+
+use crate::Result;
 use crate::cmd::Encode;
 use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
 use crate::utils::to_hex_bytes;
 
 const SKSETRBID: &[u8] = b"SKSETRBID";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {
-    rbid: [u8; 16],
+    pub rbid: [u8; 16],
 }
 
 impl Encode for Input {
@@ -15,15 +22,16 @@ impl Encode for Input {
         Payload {
             name: SKSETRBID.into(),
             args: vec![to_hex_bytes(&self.rbid)],
+            data: None,
         }
     }
 }
 
-impl Bp35c0 {
-    pub fn set_rbid(&mut self, rbid: [u8; 16]) -> Result<()> {
-        unsafe {
-            self.send(&Input { rbid })?;
-            self.wait_for_ok()
-        }
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn set_rbid(&mut self, rbid: [u8; 16]) -> Result<()> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input { rbid }).await?;
+        self.wait_for_ok_until(deadline).await
     }
 }
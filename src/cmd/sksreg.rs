@@ -1,14 +1,23 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
 use byteorder::{BigEndian, ByteOrder};
 
-use crate::{Bp35c0, Result};
+use crate::{Error, Result};
 use crate::cmd::{Decode, Encode, Response};
 use crate::payload::Payload;
-use crate::utils::{itoa, parse_hex_bytes, to_hex_bytes};
+use crate::protocol::{Io, Protocol};
+use crate::utils::{arg, itoa, parse_hex_u16, parse_hex_u32, parse_hex_u8, to_hex_bytes};
 
 const SKSREG: &[u8] = b"SKSREG";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[repr(u8)]
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Register {
     /// 自端末が使用する周波数の論理チャネル番号
     ///
@@ -22,18 +31,74 @@ pub enum Register {
     /// 初期値: 0xFFFF, 値域: 0x0000 - 0xFFFF
     S03 = 0x03,
 
+    /// MAC 層のフレームカウンタ（読み出し専用）
+    /// 暗号化されたフレームを送信するたびに増加します。
+    ///
+    /// 値域: 0x00000000 - 0xFFFFFFFF
     S07 = 0x07,
+
+    /// ペアリング ID
+    /// アクティブスキャンで応答するコーディネータを、同じペアリング ID を持つものに限定します。
+    ///
+    /// 値域: 8 桁の 16 進数
     S0A = 0x0A,
+
+    /// 型を確認できていないレジスタ（[`Kind::Unknown`]）
     S0B = 0x0B,
+
+    /// ビーコン応答フラグ
+    ///
+    /// 0: ビーコン要求に応答しない
+    /// 1: ビーコン要求に応答する
+    ///
+    /// 値域: 0 or 1
     S15 = 0x15,
+
+    /// PANA セッションライフタイム（秒）
+    /// 自動再認証フラグが有効な場合、この時間が経過する前に再認証が行われます。
+    ///
+    /// 値域: 0x00000060 - 0xFFFFFFFF
     S16 = 0x16,
+
+    /// 自動再認証フラグ
+    ///
+    /// 0: PANA セッションライフタイムが経過しても再認証しない
+    /// 1: PANA セッションライフタイムが経過する前に自動で再認証する
+    ///
+    /// 値域: 0 or 1
     S17 = 0x17,
+
+    /// 型を確認できていないレジスタ（[`Kind::Unknown`]）
     S1C = 0x1C,
+
+    /// ICMP メッセージ処理制御フラグ
+    ///
+    /// 値域: 0 or 1
     SA1 = 0xA1,
+
+    /// 型を確認できていないレジスタ（[`Kind::Unknown`]）
     SA2 = 0xA2,
+
+    /// 型を確認できていないレジスタ（[`Kind::Unknown`]）
     SA9 = 0xA9,
+
+    /// アクティブな MAC 面
+    ///
+    /// 0: B 面
+    /// 1: H 面
+    ///
+    /// 値域: 0 or 1
     SF0 = 0xF0,
+
+    /// 送信時間制限中フラグ（読み出し専用）
+    /// 電波法による送信時間の制限に達している間は 1 になり、送信できません。
+    ///
+    /// 値域: 0 or 1
     SFB = 0xFB,
+
+    /// 無線送信の積算時間（読み出し専用）
+    ///
+    /// 値域: 0x00000000 - 0xFFFFFFFF
     SFD = 0xFD,
 
     /// エコーバックフラグ
@@ -43,15 +108,187 @@ pub enum Register {
     ///
     /// 初期値: 1, 値域: 0 or 1
     SFE = 0xFE,
+
+    /// オートロードフラグ
+    ///
+    /// 0: 起動時に保存された設定を読み込まない
+    /// 1: 起動時に SKSAVE で保存された設定を読み込む
+    ///
+    /// 値域: 0 or 1
     SFF = 0xFF,
 }
 
-#[derive(Clone, Debug)]
+/// 周波数の論理チャネル番号
+///
+/// 値域: 0x21 - 0x3C
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Channel(u8);
+
+impl Channel {
+    pub const MIN: u8 = 0x21;
+    pub const MAX: u8 = 0x3C;
+
+    pub fn new(channel: u8) -> Result<Self> {
+        match channel {
+            Self::MIN..=Self::MAX => Ok(Self(channel)),
+            _ => Err(Error::OutOfRange { field: "channel" }),
+        }
+    }
+
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        Self::new(value)
+    }
+}
+
+/// PANA セッションライフタイムの下限
+pub const MIN_PANA_SESSION_LIFETIME: Duration = Duration::from_secs(0x60);
+
+/// レジスタの値の型
+///
+/// SKSREG の値は 16 進数で読み書きし、符号付きの値を持つレジスタはありません。
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// `0` または `1`
+    Bool,
+
+    /// 2 桁の 16 進数
+    Uint8,
+
+    /// 4 桁の 16 進数
+    Uint16,
+
+    /// 8 桁の 16 進数
+    Uint32,
+
+    /// 型を確認できていない
+    ///
+    /// BP35C0 で追加された S0B, S1C, SA2, SA9 は、型や値域を確認できていないのでこの型になります。
+    /// 値は受信した 16 進数の文字列のまま [`Value::Raw`] として扱い、型付きのアクセサはありません。
+    /// スナップショットの書き戻しでも、誤った値を書き込まないように対象から外しています。
+    Unknown,
+}
+
+impl Register {
+    /// すべてのレジスタ
+    pub const ALL: &'static [Register] = &[
+        Self::S02,
+        Self::S03,
+        Self::S07,
+        Self::S0A,
+        Self::S0B,
+        Self::S15,
+        Self::S16,
+        Self::S17,
+        Self::S1C,
+        Self::SA1,
+        Self::SA2,
+        Self::SA9,
+        Self::SF0,
+        Self::SFB,
+        Self::SFD,
+        Self::SFE,
+        Self::SFF,
+    ];
+
+    pub fn kind(self) -> Kind {
+        match self {
+            Self::S02 => Kind::Uint8,
+            Self::S03 => Kind::Uint16,
+            Self::S07 | Self::S0A | Self::S16 | Self::SFD => Kind::Uint32,
+            Self::S15 | Self::S17 | Self::SA1 | Self::SF0 | Self::SFB | Self::SFE | Self::SFF => {
+                Kind::Bool
+            }
+            Self::S0B | Self::S1C | Self::SA2 | Self::SA9 => Kind::Unknown,
+        }
+    }
+
+    /// 書き込み可能なレジスタかどうかを返します。
+    pub fn is_writable(self) -> bool {
+        !matches!(self, Self::S07 | Self::SFB | Self::SFD)
+    }
+
+    /// 値がこのレジスタの型と値域に収まっているかを確かめます。
+    /// 型を確認できていないレジスタでも、空の値や 16 進数でない値は受け付けません。
+    pub fn validate(self, value: &Value) -> Result<()> {
+        if value.kind() != self.kind() {
+            return Err(Error::OutOfRange { field: "value" });
+        }
+
+        match (self, value) {
+            (_, Value::Raw(raw)) if raw.is_empty() || !raw.iter().all(u8::is_ascii_hexdigit) => {
+                Err(Error::OutOfRange { field: "value" })
+            }
+            (Self::S02, Value::Uint8(u)) => Channel::new(*u).map(|_| ()),
+            (Self::S16, Value::Uint32(u))
+                if Duration::from_secs(*u as u64) < MIN_PANA_SESSION_LIFETIME =>
+            {
+                Err(Error::OutOfRange {
+                    field: "pana_session_lifetime",
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     Bool(bool),
     Uint8(u8),
     Uint16(u16),
     Uint32(u32),
+
+    /// 型を確認できていないレジスタの値
+    Raw(Vec<u8>),
+}
+
+impl Value {
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::Bool(_) => Kind::Bool,
+            Self::Uint8(_) => Kind::Uint8,
+            Self::Uint16(_) => Kind::Uint16,
+            Self::Uint32(_) => Kind::Uint32,
+            Self::Raw(_) => Kind::Unknown,
+        }
+    }
+
+    /// レジスタの型に従って値を変換します。
+    /// ファームウェアによって桁数が異なっても同じ値になるように、桁数が足りない場合は上位を 0 で埋めて解釈します。
+    pub fn decode(kind: Kind, raw: &[u8]) -> Result<Self> {
+        match kind {
+            Kind::Bool => match parse_hex_u8("value", &pad(raw, 2)?)? {
+                0 => Ok(Self::Bool(false)),
+                1 => Ok(Self::Bool(true)),
+                _ => Err(Error::parse("value", raw)),
+            },
+            Kind::Uint8 => parse_hex_u8("value", &pad(raw, 2)?).map(Self::Uint8),
+            Kind::Uint16 => parse_hex_u16("value", &pad(raw, 4)?).map(Self::Uint16),
+            Kind::Uint32 => parse_hex_u32("value", &pad(raw, 8)?).map(Self::Uint32),
+            Kind::Unknown => Ok(Self::Raw(raw.to_vec())),
+        }
+    }
+}
+
+/// 16 進数の上位を 0 で埋めて `digits` 桁にします。
+fn pad(raw: &[u8], digits: usize) -> Result<Vec<u8>> {
+    if raw.is_empty() || raw.len() > digits {
+        return Err(Error::parse("value", raw));
+    }
+
+    let mut padded = vec![b'0'; digits - raw.len()];
+    padded.extend_from_slice(raw);
+
+    Ok(padded)
 }
 
 impl From<&Value> for Vec<u8> {
@@ -60,41 +297,23 @@ impl From<&Value> for Vec<u8> {
             Value::Bool(b) => Self::from(if *b { b"1" } else { b"0" }),
             Value::Uint8(u) => itoa(*u).to_vec(),
             Value::Uint16(u) => {
-                let mut buf = [0u8, 2];
+                let mut buf = [0u8; 2];
                 BigEndian::write_u16(&mut buf, *u);
                 to_hex_bytes(&buf)
             }
             Value::Uint32(u) => {
-                let mut buf = [0u8, 4];
+                let mut buf = [0u8; 4];
                 BigEndian::write_u32(&mut buf, *u);
                 to_hex_bytes(&buf)
             }
+            Value::Raw(raw) => raw.clone(),
         }
     }
 }
 
-impl From<&[u8]> for Value {
-    fn from(value: &[u8]) -> Self {
-        if value == b"1" {
-            return Self::Bool(true);
-        }
-
-        if value == b"0" {
-            return Self::Bool(false);
-        }
-
-        match value.len() {
-            1 => match value[0] {
-                b'1' => Self::Bool(true),
-                b'0' => Self::Bool(false),
-                v => panic!("Illegal value: {}", v),
-            },
-            2 => Self::Uint8(parse_hex_bytes(value)[0]),
-            4 => Self::Uint16(BigEndian::read_u16(&parse_hex_bytes(value))),
-            8 => Self::Uint32(BigEndian::read_u32(&parse_hex_bytes(value))),
-            l => panic!("Illegal length of value: {}", l),
-        }
-    }
+/// レジスタから読み出した値が想定した型でなかった場合のエラー
+fn unexpected(value: &Value) -> Error {
+    Error::parse("value", &Vec::from(value))
 }
 
 pub struct Input {
@@ -102,6 +321,16 @@ pub struct Input {
     pub value: Option<Value>,
 }
 
+impl Input {
+    /// エコーバックフラグ (SFE) を設定する入力であれば、設定後にエコーバックが有効かどうかを返します。
+    pub(crate) fn echo_back(&self) -> Option<bool> {
+        match (self.register, &self.value) {
+            (Register::SFE, Some(Value::Bool(enabled))) => Some(*enabled),
+            _ => None,
+        }
+    }
+}
+
 impl Encode for Input {
     fn encode(&self) -> Payload {
         let mut reg = vec![b'S'];
@@ -115,6 +344,7 @@ impl Encode for Input {
         Payload {
             name: SKSREG.into(),
             args,
+            data: None,
         }
     }
 }
@@ -124,33 +354,348 @@ pub struct Output {
     pub value: Value,
 }
 
-impl Decode for Output {
-    fn decode(payload: &Payload) -> Self {
-        Self {
-            value: payload.args[0].as_slice().into(),
-        }
+impl Output {
+    /// 読み出したレジスタの型に従って応答を変換します。
+    pub(crate) fn decode_for(register: Register, raw: &RawOutput) -> Result<Self> {
+        Ok(Self {
+            value: Value::decode(register.kind(), &raw.value)?,
+        })
+    }
+}
+
+/// 型に従って変換する前の ESREG
+///
+/// 値の型は読み出したレジスタによって決まるので、応答だけでは変換できません。
+pub(crate) struct RawOutput {
+    value: Vec<u8>,
+}
+
+impl Decode for RawOutput {
+    fn decode(payload: &Payload) -> Result<Self> {
+        Ok(Self {
+            value: arg(payload, 0, "value")?.to_vec(),
+        })
     }
 }
 
-impl Response for Output {
+impl Response for RawOutput {
     const NAME: &'static [u8] = b"ESREG";
 }
 
-impl Bp35c0 {
-    pub unsafe fn read_register(&mut self, register: Register) -> Result<Output> {
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn read_register(&mut self, register: Register) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
         self.send(&Input {
             register,
             value: None,
-        })?;
-        self.wait_for_response()
+        })
+        .await?;
+        Output::decode_for(register, &self.wait_for_response_until(deadline).await?)
     }
 
-    pub unsafe fn set_register(&mut self, register: Register, value: Value) -> Result<()> {
-        self.send(&Input {
+    pub(crate) async fn set_register(&mut self, register: Register, value: Value) -> Result<()> {
+        register.validate(&value)?;
+
+        let deadline = self.io.deadline(TIMEOUT);
+        let input = Input {
             register,
             value: Some(value),
-        })?;
-        self.wait_for_ok()?;
+        };
+
+        self.send(&input).await?;
+        self.wait_for_ok_until(deadline).await?;
+
+        if let Some(enabled) = input.echo_back() {
+            self.echo.set_enabled(enabled);
+        }
+
         Ok(())
     }
+
+    async fn read_bool(&mut self, register: Register) -> Result<bool> {
+        match self.read_register(register).await?.value {
+            Value::Bool(b) => Ok(b),
+            v => Err(unexpected(&v)),
+        }
+    }
+
+    async fn read_u32(&mut self, register: Register) -> Result<u32> {
+        match self.read_register(register).await?.value {
+            Value::Uint32(u) => Ok(u),
+            v => Err(unexpected(&v)),
+        }
+    }
+
+    pub(crate) async fn channel(&mut self) -> Result<Channel> {
+        match self.read_register(Register::S02).await?.value {
+            Value::Uint8(u) => Channel::new(u),
+            v => Err(unexpected(&v)),
+        }
+    }
+
+    pub(crate) async fn set_channel(&mut self, channel: Channel) -> Result<()> {
+        self.set_register(Register::S02, Value::Uint8(channel.get()))
+            .await
+    }
+
+    pub(crate) async fn pan_id(&mut self) -> Result<u16> {
+        match self.read_register(Register::S03).await?.value {
+            Value::Uint16(u) => Ok(u),
+            v => Err(unexpected(&v)),
+        }
+    }
+
+    pub(crate) async fn set_pan_id(&mut self, pan_id: u16) -> Result<()> {
+        self.set_register(Register::S03, Value::Uint16(pan_id))
+            .await
+    }
+
+    pub(crate) async fn frame_counter(&mut self) -> Result<u32> {
+        self.read_u32(Register::S07).await
+    }
+
+    pub(crate) async fn pairing_id(&mut self) -> Result<[u8; 4]> {
+        self.read_u32(Register::S0A).await.map(u32::to_be_bytes)
+    }
+
+    pub(crate) async fn set_pairing_id(&mut self, pairing_id: [u8; 4]) -> Result<()> {
+        self.set_register(Register::S0A, Value::Uint32(u32::from_be_bytes(pairing_id)))
+            .await
+    }
+
+    pub(crate) async fn beacon_response(&mut self) -> Result<bool> {
+        self.read_bool(Register::S15).await
+    }
+
+    pub(crate) async fn set_beacon_response(&mut self, enabled: bool) -> Result<()> {
+        self.set_register(Register::S15, Value::Bool(enabled)).await
+    }
+
+    pub(crate) async fn pana_session_lifetime(&mut self) -> Result<Duration> {
+        self.read_u32(Register::S16)
+            .await
+            .map(|secs| Duration::from_secs(secs as u64))
+    }
+
+    pub(crate) async fn set_pana_session_lifetime(&mut self, lifetime: Duration) -> Result<()> {
+        let secs = u32::try_from(lifetime.as_secs()).map_err(|_| Error::OutOfRange {
+            field: "pana_session_lifetime",
+        })?;
+
+        self.set_register(Register::S16, Value::Uint32(secs)).await
+    }
+
+    pub(crate) async fn auto_reauth(&mut self) -> Result<bool> {
+        self.read_bool(Register::S17).await
+    }
+
+    pub(crate) async fn set_auto_reauth(&mut self, enabled: bool) -> Result<()> {
+        self.set_register(Register::S17, Value::Bool(enabled)).await
+    }
+
+    pub(crate) async fn icmp_handling(&mut self) -> Result<bool> {
+        self.read_bool(Register::SA1).await
+    }
+
+    pub(crate) async fn set_icmp_handling(&mut self, enabled: bool) -> Result<()> {
+        self.set_register(Register::SA1, Value::Bool(enabled)).await
+    }
+
+    pub(crate) async fn active_side(&mut self) -> Result<u8> {
+        self.read_bool(Register::SF0).await.map(u8::from)
+    }
+
+    pub(crate) async fn set_active_side(&mut self, side: u8) -> Result<()> {
+        let side = match side {
+            0 => false,
+            1 => true,
+            _ => return Err(Error::OutOfRange { field: "side" }),
+        };
+
+        self.set_register(Register::SF0, Value::Bool(side)).await
+    }
+
+    pub(crate) async fn transmit_limited(&mut self) -> Result<bool> {
+        self.read_bool(Register::SFB).await
+    }
+
+    pub(crate) async fn transmit_time(&mut self) -> Result<u32> {
+        self.read_u32(Register::SFD).await
+    }
+
+    pub(crate) async fn auto_load(&mut self) -> Result<bool> {
+        self.read_bool(Register::SFF).await
+    }
+
+    pub(crate) async fn set_auto_load(&mut self, enabled: bool) -> Result<()> {
+        self.set_register(Register::SFF, Value::Bool(enabled)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use core::time::Duration;
+
+    #[cfg(feature = "std")]
+    use crate::{Bp35c0, Error};
+    use crate::cmd::sksreg::{Channel, Kind, Register, Value};
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+
+    #[test]
+    fn test_encode_value() {
+        assert_eq!(b"0E1A".to_vec(), Vec::from(&Value::Uint16(0x0E1A)));
+        assert_eq!(b"00000E10".to_vec(), Vec::from(&Value::Uint32(0x0E10)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // 各型の最小値と最大値
+        let values = [
+            (Kind::Bool, Value::Bool(false)),
+            (Kind::Bool, Value::Bool(true)),
+            (Kind::Uint8, Value::Uint8(u8::MIN)),
+            (Kind::Uint8, Value::Uint8(u8::MAX)),
+            (Kind::Uint16, Value::Uint16(u16::MIN)),
+            (Kind::Uint16, Value::Uint16(u16::MAX)),
+            (Kind::Uint32, Value::Uint32(u32::MIN)),
+            (Kind::Uint32, Value::Uint32(u32::MAX)),
+            (Kind::Unknown, Value::Raw(b"0".to_vec())),
+            (Kind::Unknown, Value::Raw(b"0123ABCD".to_vec())),
+        ];
+
+        for (kind, value) in values {
+            assert_eq!(value, Value::decode(kind, &Vec::from(&value)).unwrap());
+        }
+
+        // ファームウェアによって桁数が異なっても同じ値になる
+        assert_eq!(Value::Bool(true), Value::decode(Kind::Bool, b"01").unwrap());
+        assert_eq!(Value::Uint8(1), Value::decode(Kind::Uint8, b"1").unwrap());
+
+        // 型の値域を超える
+        assert!(Value::decode(Kind::Bool, b"2").is_err());
+        assert!(Value::decode(Kind::Uint8, b"100").is_err());
+        assert!(Value::decode(Kind::Uint16, b"12345").is_err());
+        assert!(Value::decode(Kind::Uint32, b"123456789").is_err());
+        assert!(Value::decode(Kind::Uint8, b"").is_err());
+        assert!(Value::decode(Kind::Uint8, b"G").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        // 各レジスタについて、値域の両端と、型や値域が合わない値を確かめる
+        for &register in Register::ALL {
+            let (valid, invalid) = match register {
+                Register::S02 => (
+                    vec![Value::Uint8(Channel::MIN), Value::Uint8(Channel::MAX)],
+                    vec![
+                        Value::Uint16(0x21),
+                        Value::Uint8(Channel::MIN - 1),
+                        Value::Uint8(Channel::MAX + 1),
+                    ],
+                ),
+                Register::S03 => (
+                    vec![Value::Uint16(u16::MIN), Value::Uint16(u16::MAX)],
+                    vec![Value::Uint8(0), Value::Uint32(0)],
+                ),
+                Register::S07 | Register::S0A | Register::SFD => (
+                    vec![Value::Uint32(u32::MIN), Value::Uint32(u32::MAX)],
+                    vec![Value::Uint16(0), Value::Bool(true)],
+                ),
+                Register::S16 => (
+                    vec![Value::Uint32(0x60), Value::Uint32(u32::MAX)],
+                    vec![Value::Uint32(0x5F), Value::Uint16(0x60)],
+                ),
+                Register::S15
+                | Register::S17
+                | Register::SA1
+                | Register::SF0
+                | Register::SFB
+                | Register::SFE
+                | Register::SFF => (
+                    vec![Value::Bool(false), Value::Bool(true)],
+                    vec![Value::Uint8(1), Value::Raw(b"1".to_vec())],
+                ),
+                Register::S0B | Register::S1C | Register::SA2 | Register::SA9 => (
+                    vec![Value::Raw(b"0".to_vec()), Value::Raw(b"0123".to_vec())],
+                    vec![
+                        Value::Bool(true),
+                        Value::Raw(Vec::new()),
+                        Value::Raw(b"0G".to_vec()),
+                        Value::Raw(b"01 23".to_vec()),
+                    ],
+                ),
+            };
+
+            for value in valid {
+                assert!(register.validate(&value).is_ok(), "{register:?} {value:?}");
+            }
+
+            for value in invalid {
+                assert!(register.validate(&value).is_err(), "{register:?} {value:?}");
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_accessors() {
+        let mock = MockBp35c0::new();
+        let mut device = Bp35c0::connect(mock.clone()).unwrap();
+
+        assert!(matches!(
+            Channel::new(0x3D),
+            Err(Error::OutOfRange { field: "channel" })
+        ));
+
+        device.set_channel(Channel::new(0x3B).unwrap()).unwrap();
+        assert_eq!(0x3B, device.channel().unwrap().get());
+        assert_eq!(Some(b"3B".to_vec()), mock.register(b"S02"));
+
+        device.set_pan_id(0x1234).unwrap();
+        assert_eq!(0x1234, device.pan_id().unwrap());
+
+        device.set_pairing_id([0x12, 0x34, 0x56, 0x78]).unwrap();
+        assert_eq!([0x12, 0x34, 0x56, 0x78], device.pairing_id().unwrap());
+        assert_eq!(Some(b"12345678".to_vec()), mock.register(b"S0A"));
+
+        assert_eq!(
+            Duration::from_secs(3600),
+            device.pana_session_lifetime().unwrap()
+        );
+        assert!(matches!(
+            device.set_pana_session_lifetime(Duration::from_secs(0x5F)),
+            Err(Error::OutOfRange { .. })
+        ));
+        device
+            .set_pana_session_lifetime(Duration::from_secs(0x60))
+            .unwrap();
+        assert_eq!(Some(b"00000060".to_vec()), mock.register(b"S16"));
+
+        device.set_auto_reauth(false).unwrap();
+        assert!(!device.auto_reauth().unwrap());
+        assert!(device.beacon_response().unwrap());
+        assert_eq!(0, device.frame_counter().unwrap());
+
+        device.set_icmp_handling(false).unwrap();
+        assert!(!device.icmp_handling().unwrap());
+        assert_eq!(Some(b"0".to_vec()), mock.register(b"SA1"));
+
+        device.set_active_side(1).unwrap();
+        assert_eq!(1, device.active_side().unwrap());
+        assert_eq!(Some(b"1".to_vec()), mock.register(b"SF0"));
+        assert!(matches!(
+            device.set_active_side(2),
+            Err(Error::OutOfRange { field: "side" })
+        ));
+
+        assert!(!device.transmit_limited().unwrap());
+        assert_eq!(0, device.transmit_time().unwrap());
+
+        device.set_auto_load(true).unwrap();
+        assert!(device.auto_load().unwrap());
+        assert_eq!(Some(b"1".to_vec()), mock.register(b"SFF"));
+    }
 }
@@ -0,0 +1,59 @@
+use alloc::vec;
+use core::time::Duration;
+
+use crate::Result;
+use crate::cmd::Encode;
+use crate::event::EventBody;
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+
+const SKTERM: &[u8] = b"SKTERM";
+
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct Input {}
+
+impl Encode for Input {
+    fn encode(&self) -> Payload {
+        Payload {
+            name: SKTERM.into(),
+            args: vec![],
+            data: None,
+        }
+    }
+}
+
+/// PANA セッションの終了結果
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Output {
+    /// セッションの終了に成功した (EVENT 27)
+    Terminated,
+
+    /// 相手から応答が無いままタイムアウトした (EVENT 28)
+    TimedOut,
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn terminate(&mut self) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_ok_until(deadline).await?;
+
+        let event = self
+            .wait_for_event_until(deadline, |e| {
+                matches!(
+                    e.body,
+                    EventBody::PanaTerminated | EventBody::PanaTerminationTimeout
+                )
+            })
+            .await?;
+
+        Ok(match event.body {
+            EventBody::PanaTerminated => Output::Terminated,
+            _ => Output::TimedOut,
+        })
+    }
+}
@@ -1,9 +1,18 @@
-use crate::{Bp35c0, Result};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use core::time::Duration;
+
+use crate::Result;
 use crate::cmd::{Decode, Encode, Response};
 use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+use crate::utils::arg;
 
 const SKVER: &[u8] = b"SKVER";
 
+/// コマンドの応答を待つ既定の時間
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Clone, Debug)]
 pub struct Input {}
 
@@ -12,6 +21,7 @@ impl Encode for Input {
         Payload {
             name: SKVER.into(),
             args: vec![],
+            data: None,
         }
     }
 }
@@ -22,10 +32,10 @@ pub struct Output {
 }
 
 impl Decode for Output {
-    fn decode(payload: &Payload) -> Self {
-        Self {
-            version: String::from_utf8_lossy(&payload.args[0]).to_string(),
-        }
+    fn decode(payload: &Payload) -> Result<Self> {
+        Ok(Self {
+            version: String::from_utf8_lossy(arg(payload, 0, "version")?).to_string(),
+        })
     }
 }
 
@@ -33,11 +43,11 @@ impl Response for Output {
     const NAME: &'static [u8] = b"EVER";
 }
 
-impl Bp35c0 {
-    pub fn version(&mut self) -> Result<Output> {
-        unsafe {
-            self.send(&Input {})?;
-            self.wait_for_response::<Output>()
-        }
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn version(&mut self) -> Result<Output> {
+        let deadline = self.io.deadline(TIMEOUT);
+
+        self.send(&Input {}).await?;
+        self.wait_for_response_until::<Output>(deadline).await
     }
 }
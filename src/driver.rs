@@ -0,0 +1,161 @@
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::net::Ipv6Addr;
+use std::time::{Duration, Instant};
+
+use serialport::SerialPort;
+
+use crate::{Error, Result, Transport};
+use crate::event::Event;
+use crate::event::erxudp::ERxUdp;
+use crate::framer::Framer;
+use crate::protocol::{block_on, commands, Io, Protocol};
+use crate::session::{session, Connected, Joined};
+
+pub struct Bp35c0<Port: Transport = Box<dyn SerialPort>> {
+    pub(crate) core: Protocol<StdIo<Port>>,
+}
+
+/// 同期版のドライバのセッション
+pub type Session<State, Port = Box<dyn SerialPort>> = crate::session::Session<State, Bp35c0<Port>>;
+
+/// 同期版のドライバの通信路
+pub(crate) struct StdIo<Port> {
+    pub(crate) reader: BufReader<Port>,
+}
+
+/// 読み込みを再試行すべきエラーかどうかを判定します。
+pub(crate) fn is_transient(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::TimedOut | ErrorKind::WouldBlock | ErrorKind::Interrupted
+    )
+}
+
+impl<Port: Transport> Io for StdIo<Port> {
+    type Instant = Instant;
+
+    fn deadline(&self, timeout: Duration) -> Option<Instant> {
+        Some(Instant::now() + timeout)
+    }
+
+    /// ポートの読み込みタイムアウトは期限に達するまで読み込みを再試行します。
+    /// 期限の判定はポートの読み込みタイムアウトごとに行われるため、その精度はポートの設定に依存します。
+    async fn fill(&mut self, framer: &mut Framer, deadline: Option<Instant>) -> Result<()> {
+        loop {
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Err(Error::Timeout);
+            }
+
+            match self.reader.fill_buf() {
+                Ok([]) => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+                Ok(bytes) => {
+                    let len = bytes.len();
+                    framer.push(bytes);
+                    self.reader.consume(len);
+                    return Ok(());
+                }
+                Err(e) if is_transient(&e) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.reader.get_mut().write_all(bytes)?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.reader.get_mut().flush()?;
+        Ok(())
+    }
+}
+
+/// [`commands`] の一覧から、応答を受信するまでブロックする公開メソッドを生成します。
+macro_rules! blocking {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            $vis fn $name(&mut self, $($arg: $ty),*) -> Result<$ret> {
+                block_on(self.core.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl<Port: Transport> Bp35c0<Port> {
+    pub fn connect(transport: Port) -> Result<Self> {
+        let io = StdIo {
+            reader: BufReader::new(transport),
+        };
+
+        Ok(Self {
+            core: block_on(Protocol::connect(io))?,
+        })
+    }
+
+    commands!(blocking);
+
+    pub fn wait_for_event<F>(&mut self, criteria: F) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        self.wait_for_event_until(None, criteria)
+    }
+
+    pub fn wait_for_event_until<F>(
+        &mut self,
+        deadline: Option<Instant>,
+        criteria: F,
+    ) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        block_on(self.core.wait_for_event_until(deadline, criteria))
+    }
+
+    pub(crate) fn wait_for_udp<F>(&mut self, criteria: F) -> Result<ERxUdp>
+    where
+        F: Fn(&ERxUdp) -> bool,
+    {
+        block_on(self.core.wait_for_udp(criteria))
+    }
+
+    /// 指定したアドレスに ICMP Echo request を送信し、Echo reply (EPONG) を受信するまでの時間を返します。
+    /// [`crate::cmd::skping::TIMEOUT`] 以内に応答が無い場合は [`Error::Timeout`] を返します。
+    pub fn ping(&mut self, ip_addr: Ipv6Addr, side: u8) -> Result<Duration> {
+        let start = Instant::now();
+        block_on(self.core.ping(ip_addr, side))?;
+        Ok(start.elapsed())
+    }
+
+    /// 通信路への参照を返します。
+    pub(crate) fn port_mut(&mut self) -> &mut Port {
+        self.core.io.reader.get_mut()
+    }
+
+    /// 受信済みでまだ処理されていないデータをすべて破棄します。
+    pub(crate) fn discard_input(&mut self) {
+        self.core.discard_input();
+
+        let reader = &mut self.core.io.reader;
+        let len = reader.buffer().len();
+        reader.consume(len);
+    }
+}
+
+session!(blocking, impl<Port> Bp35c0<Port> where Port: Transport);
+
+impl<Port: Transport> Session<Connected, Port> {
+    pub fn connect(transport: Port) -> Result<Self> {
+        Ok(Self::from(Bp35c0::connect(transport)?))
+    }
+}
+
+impl<Port: Transport> Session<Joined, Port> {
+    /// コーディネータとの往復時間を計測します。
+    pub fn ping(&mut self) -> Result<Duration> {
+        let (coordinator, side) = (self.coordinator(), self.pan().side);
+        self.device().ping(coordinator, side)
+    }
+}
@@ -0,0 +1,109 @@
+//! 送信したコマンドのエコーバックの判別
+//!
+//! エコーバックが有効な間は送信したコマンドを記録しておき、受信した行がそのいずれかと完全に一致した場合にだけエコーバックとみなします。
+//! 名前の接頭辞で判別しないので、`SK` や `R` で始まる応答を取り違えることはありません。
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+pub(crate) struct EchoBack {
+    enabled: bool,
+
+    /// 送信したが、まだエコーバックを受信していないコマンド
+    pending: VecDeque<Vec<u8>>,
+}
+
+impl Default for EchoBack {
+    /// 起動直後やリセット直後はエコーバックが有効 (SFE = 1) なので、有効な状態から始めます。
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl EchoBack {
+    /// SFE の変更やリセットに合わせて、エコーバックの有無を切り替えます。
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.pending.clear();
+    }
+
+    /// 送信したコマンドを（CRLF を除いて）記録します。
+    pub(crate) fn sent(&mut self, command: &[u8]) {
+        if self.enabled {
+            self.pending.push_back(command.to_vec());
+        }
+    }
+
+    /// 受信した行が送信したコマンドのエコーバックであれば、記録から取り除いて `true` を返します。
+    /// それより前に送信したコマンドのエコーバックは受信し損ねたものとして、併せて取り除きます。
+    pub(crate) fn strip(&mut self, line: &[u8]) -> bool {
+        match self.pending.iter().position(|c| c == line) {
+            Some(pos) => {
+                self.pending.drain(..=pos);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 受信済みのデータを破棄した際に、エコーバックの待ちも破棄します。
+    #[cfg(feature = "std")]
+    pub(crate) fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::Bp35c0;
+    #[cfg(feature = "std")]
+    use crate::cmd::sksreg;
+    use crate::echo::EchoBack;
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+
+    #[test]
+    fn test_strip() {
+        let mut echo = EchoBack::default();
+
+        echo.sent(b"SKSREG S02");
+        echo.sent(b"SKVER");
+        assert!(!echo.strip(b"ESREG 21"));
+        assert!(echo.strip(b"SKVER"));
+        assert!(!echo.strip(b"SKSREG S02"));
+
+        echo.set_enabled(false);
+        echo.sent(b"SKVER");
+        assert!(!echo.strip(b"SKVER"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_echo_enabled() {
+        let mock = MockBp35c0::new();
+        let mut device = Bp35c0::connect(mock.clone()).unwrap();
+
+        device
+            .raw()
+            .set_register(sksreg::Register::SFE, sksreg::Value::Bool(true))
+            .unwrap();
+        assert_eq!(Some(b"1".to_vec()), mock.register(b"SFE"));
+
+        // エコーバックを読み捨て、送信していない SK や R で始まる行はそのまま受け取る
+        assert!(device.version().is_ok());
+        mock.push_line(b"SKVER");
+        mock.push_line(b"RESULT");
+        assert_eq!(
+            b"SKVER".to_vec(),
+            device.raw().receive_payload().unwrap().name
+        );
+        assert_eq!(
+            b"RESULT".to_vec(),
+            device.raw().receive_payload().unwrap().name
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! embedded-io を用いた `no_std` 環境向けのドライバ
+//!
+//! コマンドの送信内容や応答の解釈は同期版の [`crate::Bp35c0`] と共通で、通信路だけが [`embedded_io`] のトレイトになっています。
+//! PAN への参加や UDP の送受信は、同期版と同じく [`Session`] を通して行います。
+//!
+//! [`Bp35c0::connect`] で接続したドライバは時計を持たないため、期限を管理しません。
+//! この場合に応答を待つ時間を制限したければ、通信路の読み込みが [`ErrorKind::TimedOut`] を返すようにしてください（[`Error::Timeout`] として扱います）。
+//!
+//! [`Bp35c0::connect_with_clock`] で [`Clock`] を渡すと、同期版と同じ期限でコマンドの応答を待ちます。
+//! 期限の判定は読み込みの合間に行われるので、通信路の読み込みは何も受信できなくても定期的に
+//! [`ErrorKind::TimedOut`] を返すようにしてください（期限までは読み込みを再試行します）。
+
+use core::time::Duration;
+
+use embedded_io::{Error as _, ErrorKind, Read, Write};
+
+use crate::{Error, Result};
+use crate::event::Event;
+use crate::event::erxudp::ERxUdp;
+use crate::framer::Framer;
+use crate::protocol::{block_on, commands, Io, Protocol};
+use crate::session::{session, Connected};
+
+/// 1 回の読み込みで受け取るバイト数の上限
+const CHUNK_SIZE: usize = 64;
+
+/// 応答を待つ期限の判定に使う時計
+pub trait Clock {
+    /// 任意の基準時点からの経過時間を返します。値は単調に増加しなければなりません。
+    fn now(&self) -> Duration;
+}
+
+/// 時計を持たないドライバの型引数
+///
+/// 値を作ることはできず、[`Bp35c0::connect`] で接続したドライバは期限を管理しません。
+pub enum NoClock {}
+
+impl Clock for NoClock {
+    fn now(&self) -> Duration {
+        match *self {}
+    }
+}
+
+/// embedded-io 版の BP35C0 ドライバ
+pub struct Bp35c0<Port, C = NoClock> {
+    core: Protocol<EmbeddedIo<Port, C>>,
+}
+
+/// embedded-io 版のドライバのセッション
+pub type Session<State, Port, C = NoClock> = crate::session::Session<State, Bp35c0<Port, C>>;
+
+/// embedded-io 版のドライバの通信路
+struct EmbeddedIo<Port, C> {
+    port: Port,
+    clock: Option<C>,
+}
+
+fn transport_error<E>(e: E) -> Error
+where
+    E: embedded_io::Error,
+{
+    match e.kind() {
+        ErrorKind::TimedOut => Error::Timeout,
+        k => Error::Transport(k),
+    }
+}
+
+impl<Port, C> Io for EmbeddedIo<Port, C>
+where
+    Port: Read + Write,
+    C: Clock,
+{
+    type Instant = Duration;
+
+    fn deadline(&self, timeout: Duration) -> Option<Duration> {
+        self.clock.as_ref().map(|c| c.now() + timeout)
+    }
+
+    async fn fill(&mut self, framer: &mut Framer, deadline: Option<Duration>) -> Result<()> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+
+        loop {
+            if let (Some(deadline), Some(clock)) = (deadline, &self.clock) {
+                if clock.now() >= deadline {
+                    return Err(Error::Timeout);
+                }
+            }
+
+            match self.port.read(&mut chunk) {
+                Ok(0) => return Err(Error::Transport(ErrorKind::NotConnected)),
+                Ok(len) => {
+                    framer.push(&chunk[..len]);
+                    return Ok(());
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                // 時計があれば、読み込みのタイムアウトは期限の判定のために再試行する
+                Err(e) if e.kind() == ErrorKind::TimedOut && self.clock.is_some() => {}
+                Err(e) => return Err(transport_error(e)),
+            }
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.port.write_all(bytes).map_err(transport_error)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.port.flush().map_err(transport_error)
+    }
+}
+
+/// [`commands`] の一覧から、応答を受信するまでブロックする公開メソッドを生成します。
+macro_rules! blocking {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            $vis fn $name(&mut self, $($arg: $ty),*) -> Result<$ret> {
+                block_on(self.core.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl<Port> Bp35c0<Port>
+where
+    Port: Read + Write,
+{
+    /// 時計を持たないドライバとして接続します。応答を待つ期限は管理しません。
+    pub fn connect(port: Port) -> Result<Self> {
+        Self::connect_inner(port, None)
+    }
+}
+
+impl<Port, C> Bp35c0<Port, C>
+where
+    Port: Read + Write,
+    C: Clock,
+{
+    /// `clock` で期限を判定するドライバとして接続します。
+    pub fn connect_with_clock(port: Port, clock: C) -> Result<Self> {
+        Self::connect_inner(port, Some(clock))
+    }
+
+    fn connect_inner(port: Port, clock: Option<C>) -> Result<Self> {
+        let io = EmbeddedIo { port, clock };
+
+        Ok(Self {
+            core: block_on(Protocol::connect(io))?,
+        })
+    }
+
+    /// ドライバを破棄し、通信路を取り出します。
+    pub fn into_inner(self) -> Port {
+        self.core.io.port
+    }
+
+    commands!(blocking);
+
+    pub fn wait_for_event<F>(&mut self, criteria: F) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        block_on(self.core.wait_for_event_until(None, criteria))
+    }
+
+    pub fn wait_for_udp<F>(&mut self, criteria: F) -> Result<ERxUdp>
+    where
+        F: Fn(&ERxUdp) -> bool,
+    {
+        block_on(self.core.wait_for_udp(criteria))
+    }
+}
+
+session!(blocking, impl<Port, C> Bp35c0<Port, C> where Port: Read + Write, C: Clock);
+
+impl<Port> Session<Connected, Port>
+where
+    Port: Read + Write,
+{
+    /// 時計を持たないドライバとして接続します。応答を待つ期限は管理しません。
+    pub fn connect(port: Port) -> Result<Self> {
+        Ok(Self::from(Bp35c0::connect(port)?))
+    }
+}
+
+impl<Port, C> Session<Connected, Port, C>
+where
+    Port: Read + Write,
+    C: Clock,
+{
+    /// `clock` で期限を判定するドライバとして接続します。
+    pub fn connect_with_clock(port: Port, clock: C) -> Result<Self> {
+        Ok(Self::from(Bp35c0::connect_with_clock(port, clock)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use core::time::Duration;
+
+    use embedded_io::{ErrorKind, ErrorType, Read, Write};
+
+    use alloc::collections::BTreeMap;
+
+    use crate::Error;
+    use crate::cmd::skver;
+    use crate::embedded::{Bp35c0, Clock, Session};
+    use crate::event::UDPSendResult;
+    use crate::event::epandesc::EPanDesc;
+
+    /// 決められた応答を返し、送信された内容を記録する通信路
+    struct Script<'a> {
+        rx: &'a [u8],
+        tx: Vec<u8>,
+    }
+
+    impl ErrorType for Script<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for Script<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            self.rx.read(buf)
+        }
+    }
+
+    impl Write for Script<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    /// 受信するものが無くなると読み込みのタイムアウトを返し、そのたびに時計を 1 秒進める通信路
+    struct Stalled<'a> {
+        rx: &'a [u8],
+        now: &'a Cell<Duration>,
+    }
+
+    impl ErrorType for Stalled<'_> {
+        type Error = ErrorKind;
+    }
+
+    impl Read for Stalled<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            if self.rx.is_empty() {
+                self.now.set(self.now.get() + Duration::from_secs(1));
+                return Err(ErrorKind::TimedOut);
+            }
+
+            Ok(self.rx.read(buf).unwrap())
+        }
+    }
+
+    impl Write for Stalled<'_> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct FakeClock<'a>(&'a Cell<Duration>);
+
+    impl Clock for FakeClock<'_> {
+        fn now(&self) -> Duration {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_connect_and_scan() {
+        // SKRESET, SKSREG, SKSCAN への応答
+        let port = Script {
+            rx: b"OK\r\nOK\r\nOK\r\nEPANDESC\r\n  Channel:3B\r\n  Channel Page:09\r\n  Pan ID:1234\r\n  Addr:001D129000000002\r\n  LQI:E1\r\n  Side:0\r\nEVENT 22 FE80:0000:0000:0000:021D:1290:1234:5678 0\r\n",
+            tx: Vec::new(),
+        };
+
+        let mut device = Bp35c0::connect(port).unwrap();
+        let descs = device.scan_active(false, 0xFFFFFFFF, 6, 0).unwrap();
+        assert_eq!(1, descs.len());
+        assert_eq!(0x1234, descs[0].pan_id);
+        assert_eq!(
+            [0x00, 0x1D, 0x12, 0x90, 0x00, 0x00, 0x00, 0x02],
+            descs[0].addr
+        );
+
+        // 応答が尽きたら通信路が切れたものとして扱う
+        assert!(matches!(device.version(), Err(Error::Transport(_))));
+
+        let sent = device.into_inner().tx;
+        assert_eq!(
+            b"\r\nSKRESET\r\nSKSREG SFE 0\r\nSKSCAN 3 FFFFFFFF 06 0\r\nSKVER\r\n",
+            sent.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_session() {
+        const SKSENDTO: &[u8] =
+            b"SKSENDTO 1 FE80:0000:0000:0000:021D:1290:0000:0002 0E1A 1 0 0001 a\r\n";
+
+        // SKRESET, SKSREG, SKSETRBID, SKSETPWD, SKSREG S02, SKSREG S03, SKLL64, SKJOIN, SKSENDTO への応答
+        let port = Script {
+            rx: b"OK\r\nOK\r\nOK\r\nOK\r\nOK\r\nOK\r\nFE80:0000:0000:0000:021D:1290:0000:0002\r\nOK\r\nEVENT 25 FE80:0000:0000:0000:021D:1290:0000:0002 0\r\nEVENT 21 FE80:0000:0000:0000:021D:1290:0000:0002 0 00\r\nOK\r\n",
+            tx: Vec::new(),
+        };
+
+        // std を使わないビルドでは `crate::mock::test_pan` を使えないため、同じ記述子をここで組み立てる
+        let pan = EPanDesc {
+            channel: 0x3B,
+            channel_page: 0x09,
+            pan_id: 0x1234,
+            addr: [0x00, 0x1D, 0x12, 0x90, 0x00, 0x00, 0x00, 0x02],
+            lqi: 0xE1,
+            side: 0,
+            pair_id: None,
+            extras: BTreeMap::new(),
+        };
+
+        // UDP の送信は PAN に参加したセッションからのみ行える
+        let session = Session::connect(port).unwrap();
+        let session = session.authenticate([0x30; 16], b"PASSWORD").unwrap();
+        let mut session = session.join(&pan).unwrap();
+        assert_eq!("fe80::21d:1290:0:2".parse(), Ok(session.coordinator()));
+        assert!(matches!(
+            session.send_udp(0x0E1A, b"a"),
+            Ok(UDPSendResult::Success)
+        ));
+
+        let sent = session.into_inner().into_inner().tx;
+        assert!(sent.ends_with(SKSENDTO));
+    }
+
+    #[test]
+    fn test_scan_energy() {
+        // SKRESET, SKSREG, SKSCAN への応答（EEDSCAN が EVENT 1F より先に届く場合）
+        let port = Script {
+            rx: b"OK\r\nOK\r\nOK\r\nEEDSCAN\r\n21 3C 22 42\r\nEVENT 1F FE80:0000:0000:0000:021D:1290:1234:5678 0\r\n",
+            tx: Vec::new(),
+        };
+
+        let mut device = Bp35c0::connect(port).unwrap();
+        let levels = device.scan_energy(0x3, 4, 0).unwrap();
+        assert_eq!(2, levels.len());
+        assert_eq!(0x42, levels[1].rssi);
+    }
+
+    #[test]
+    fn test_deadline() {
+        // SKRESET, SKSREG への応答
+        let now = Cell::new(Duration::ZERO);
+        let port = Stalled {
+            rx: b"OK\r\nOK\r\n",
+            now: &now,
+        };
+
+        // 時計があれば、読み込みのタイムアウトを再試行して期限まで待つ
+        let mut device = Bp35c0::connect_with_clock(port, FakeClock(&now)).unwrap();
+        let start = now.get();
+        assert!(matches!(device.version(), Err(Error::Timeout)));
+        assert_eq!(skver::TIMEOUT, now.get() - start);
+    }
+
+    #[test]
+    fn test_no_clock() {
+        // 時計が無ければ、読み込みのタイムアウトをそのまま返す
+        let now = Cell::new(Duration::ZERO);
+        let port = Stalled {
+            rx: b"OK\r\nOK\r\n",
+            now: &now,
+        };
+
+        let mut device = Bp35c0::connect(port).unwrap();
+        assert!(matches!(device.version(), Err(Error::Timeout)));
+        assert_eq!(Duration::from_secs(1), now.get());
+    }
+}
@@ -0,0 +1,215 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
+
+use crate::payload::Payload;
+
+pub(crate) const FAIL: &[u8] = b"FAIL";
+
+/// `FAIL ERxx` で通知されるエラーコード
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FailCode {
+    /// ER04: 指定されたコマンドがサポートされていない
+    UnsupportedCommand,
+
+    /// ER05: 指定されたコマンドの引数の数が正しくない
+    InvalidArgumentCount,
+
+    /// ER06: 指定されたコマンドの引数形式や値域が正しくない
+    ArgumentOutOfRange,
+
+    /// ER09: UART 入力エラーが発生した
+    UartInputError,
+
+    /// ER10: 指定されたコマンドは受付けたが、実行結果が失敗した
+    CommandFailed,
+
+    /// 上記以外のエラーコード
+    Unknown(u8),
+}
+
+impl From<u8> for FailCode {
+    fn from(value: u8) -> Self {
+        match value {
+            4 => Self::UnsupportedCommand,
+            5 => Self::InvalidArgumentCount,
+            6 => Self::ArgumentOutOfRange,
+            9 => Self::UartInputError,
+            10 => Self::CommandFailed,
+            c => Self::Unknown(c),
+        }
+    }
+}
+
+impl From<&Payload> for FailCode {
+    fn from(value: &Payload) -> Self {
+        let code = value
+            .args
+            .first()
+            .and_then(|a| a.strip_prefix(b"ER"))
+            .and_then(|c| core::str::from_utf8(c).ok())
+            .and_then(|c| c.parse::<u8>().ok())
+            .unwrap_or(0);
+
+        Self::from(code)
+    }
+}
+
+impl Display for FailCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedCommand => write!(f, "ER04: unsupported command"),
+            Self::InvalidArgumentCount => write!(f, "ER05: invalid number of arguments"),
+            Self::ArgumentOutOfRange => write!(f, "ER06: argument out of range"),
+            Self::UartInputError => write!(f, "ER09: UART input error"),
+            Self::CommandFailed => write!(f, "ER10: command failed"),
+            Self::Unknown(c) => write!(f, "ER{c:02}: unknown error"),
+        }
+    }
+}
+
+/// フラッシュメモリに対する操作
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FlashOperation {
+    /// 設定の保存 (SKSAVE)
+    Save,
+
+    /// 設定の読み込み (SKLOAD)
+    Load,
+
+    /// 設定の消去 (SKERASE)
+    Erase,
+}
+
+impl Display for FlashOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Save => write!(f, "save"),
+            Self::Load => write!(f, "load"),
+            Self::Erase => write!(f, "erase"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// ポートの読み書きに失敗した
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+
+    /// シリアルポートの操作に失敗した
+    #[cfg(feature = "std")]
+    Serial(serialport::Error),
+
+    /// embedded-io の通信路の読み書きに失敗した
+    #[cfg(feature = "embedded-io")]
+    Transport(embedded_io::ErrorKind),
+
+    /// 応答を待っている間にタイムアウトした
+    Timeout,
+
+    /// モジュールからの応答を解釈できなかった
+    Parse { field: &'static str, raw: Vec<u8> },
+
+    /// 引数が値域を外れている
+    OutOfRange { field: &'static str },
+
+    /// モジュールが `FAIL ERxx` を返した
+    Fail(FailCode),
+
+    /// PANA 認証に失敗した (EVENT 24)
+    JoinFailed,
+
+    /// スキャンで参加できる PAN が見つからなかった
+    PanNotFound,
+
+    /// フラッシュメモリの操作 (SKSAVE, SKLOAD, SKERASE) に対してモジュールが `FAIL ERxx` を返した
+    ///
+    /// リファレンスにはこれらのコマンドが返すエラーコードごとの意味が書かれていないため、コードはそのまま保持します。
+    /// たとえば ER10 は「コマンドは受付けたが、実行結果が失敗した」ことだけを表します。
+    Flash {
+        operation: FlashOperation,
+        code: FailCode,
+    },
+}
+
+impl Error {
+    pub(crate) fn parse(field: &'static str, raw: &[u8]) -> Self {
+        Self::Parse {
+            field,
+            raw: raw.to_vec(),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            #[cfg(feature = "std")]
+            Self::Serial(e) => write!(f, "Serial port error: {e}"),
+            #[cfg(feature = "embedded-io")]
+            Self::Transport(k) => write!(f, "Transport error: {k:?}"),
+            Self::Timeout => write!(f, "Timed out"),
+            Self::Parse { field, raw } => write!(
+                f,
+                "Failed to parse {field}: {:?}",
+                String::from_utf8_lossy(raw),
+            ),
+            Self::OutOfRange { field } => write!(f, "{field} is out of range"),
+            Self::Fail(c) => write!(f, "Command failed with {c}"),
+            Self::JoinFailed => write!(f, "PANA authentication failed"),
+            Self::PanNotFound => write!(f, "No PAN found"),
+            Self::Flash { operation, code } => write!(f, "Flash {operation} failed with {code}"),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => Some(e),
+            #[cfg(feature = "std")]
+            Self::Serial(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<serialport::Error> for Error {
+    fn from(value: serialport::Error) -> Self {
+        Self::Serial(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::FailCode;
+    use crate::payload::Payload;
+
+    #[test]
+    fn test_fail_code() {
+        assert_eq!(
+            FailCode::UnsupportedCommand,
+            FailCode::from(&Payload::from(b"FAIL ER04".to_vec())),
+        );
+        assert_eq!(
+            FailCode::CommandFailed,
+            FailCode::from(&Payload::from(b"FAIL ER10".to_vec())),
+        );
+        assert_eq!(
+            FailCode::Unknown(1),
+            FailCode::from(&Payload::from(b"FAIL ER01".to_vec())),
+        );
+    }
+}
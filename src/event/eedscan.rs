@@ -0,0 +1,49 @@
+use alloc::vec::Vec;
+
+use crate::Result;
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+use crate::utils::parse_hex_u8;
+
+pub(crate) const EEDSCAN: &[u8] = b"EEDSCAN";
+
+/// ED スキャンで計測されたチャネルごとのエネルギーレベル
+#[derive(Copy, Clone, Debug)]
+pub struct EnergyLevel {
+    pub channel: u8,
+    pub rssi: u8,
+}
+
+impl<I: Io> Protocol<I> {
+    /// EEDSCAN に続いて出力される `<CHANNEL> <RSSI> ...` の行を読み込みます。
+    pub(crate) async fn receive_eedscan_until(
+        &mut self,
+        deadline: Option<I::Instant>,
+        payload: &Payload,
+    ) -> Result<Vec<EnergyLevel>> {
+        if payload.args.is_empty() {
+            let line = self.receive_frame_until(deadline).await?;
+            parse_energy_levels(&split_args(&line))
+        } else {
+            parse_energy_levels(&payload.args)
+        }
+    }
+}
+
+pub(crate) fn split_args(line: &[u8]) -> Vec<Vec<u8>> {
+    line.split(|b| *b == b' ')
+        .filter(|a| !a.is_empty())
+        .map(|a| a.to_vec())
+        .collect()
+}
+
+pub(crate) fn parse_energy_levels(args: &[Vec<u8>]) -> Result<Vec<EnergyLevel>> {
+    args.chunks_exact(2)
+        .map(|pair| {
+            Ok(EnergyLevel {
+                channel: parse_hex_u8("channel", &pair[0])?,
+                rssi: parse_hex_u8("rssi", &pair[1])?,
+            })
+        })
+        .collect()
+}
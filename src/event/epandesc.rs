@@ -1,13 +1,27 @@
-use byteorder::{BigEndian, ByteOrder};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+
 use tracing::debug;
 
-use crate::{Bp35c0, Result};
+use crate::Result;
 use crate::payload::Payload;
-use crate::utils::parse_hex_bytes;
+use crate::protocol::{Io, Protocol};
+use crate::utils::{parse_flag, parse_hex_array, parse_hex_u16, parse_hex_u8};
 
 pub(crate) const EPANDESC: &[u8] = b"EPANDESC";
 
-#[derive(Clone, Debug)]
+const CHANNEL: &str = "Channel";
+const CHANNEL_PAGE: &str = "Channel Page";
+const PAN_ID: &str = "Pan ID";
+const ADDR: &str = "Addr";
+const LQI: &str = "LQI";
+const SIDE: &str = "Side";
+const PAIR_ID: &str = "PairID";
+
+const KEYS: &[&str] = &[CHANNEL, CHANNEL_PAGE, PAN_ID, ADDR, LQI, SIDE, PAIR_ID];
+
+#[derive(Clone, Debug, Default)]
 pub struct EPanDesc {
     pub channel: u8,
     pub channel_page: u8,
@@ -15,51 +29,103 @@ pub struct EPanDesc {
     pub addr: [u8; 8],
     pub lqi: u8,
     pub side: u8,
-    pub pair_id: Option<[u8; 2]>,
+    pub pair_id: Option<[u8; 4]>,
+
+    /// 上記以外のキーで出力された値
+    pub extras: BTreeMap<String, Vec<u8>>,
 }
 
-impl Bp35c0 {
-    pub unsafe fn receive_epandesc(&mut self) -> Result<EPanDesc> {
-        let mut desc = EPanDesc {
-            channel: 0,
-            channel_page: 0,
-            pan_id: 0,
-            addr: [0u8; 8],
-            lqi: 0,
-            side: 0,
-            pair_id: None,
-        };
+/// EPANDESC に続く 1 行を `desc` に反映します。
+/// EPANDESC の一部でない行であれば `None` を、最終行であれば `Some(true)` を返します。
+pub(crate) fn apply_line(desc: &mut EPanDesc, line: &[u8]) -> Result<Option<bool>> {
+    let (key, value) = match split_field(line) {
+        Some(f) => f,
+        _ => return Ok(None),
+    };
 
-        loop {
-            let line = self.receive_until_crlf()?;
+    match key.as_str() {
+        CHANNEL => desc.channel = parse_hex_u8("channel", value)?,
+        CHANNEL_PAGE => desc.channel_page = parse_hex_u8("channel_page", value)?,
+        PAN_ID => desc.pan_id = parse_hex_u16("pan_id", value)?,
+        ADDR => desc.addr = parse_hex_array("addr", value)?,
+        LQI => desc.lqi = parse_hex_u8("lqi", value)?,
+        SIDE => desc.side = parse_flag("side", value)? as u8,
+        PAIR_ID => {
+            desc.pair_id = Some(parse_hex_array("pair_id", value)?);
 
-            debug!("< {}", String::from_utf8_lossy(&line));
+            // PairID は EPANDESC の最終行
+            return Ok(Some(true));
+        }
+        _ => {
+            desc.extras.insert(key, value.to_vec());
+        }
+    }
 
-            let line = match line.strip_prefix(b"  ") {
-                Some(l) => l,
-                _ => {
-                    self.buf.push_back(Payload::from(line));
-                    break;
-                }
-            };
+    Ok(Some(false))
+}
 
-            if let Some(ch) = line.strip_prefix(b"Channel:") {
-                desc.channel = parse_hex_bytes(ch)[0];
-            }
+/// `  Key:Value` 形式の行をキーと値に分割します。
+/// インデントされていない行は、既知のキーである場合のみ EPANDESC の一部とみなします。
+fn split_field(line: &[u8]) -> Option<(String, &[u8])> {
+    let (indented, line) = match line.strip_prefix(b"  ") {
+        Some(l) => (true, l),
+        _ => (false, line),
+    };
 
-            if let Some(chp) = line.strip_prefix(b"Channel Page:") {
-                desc.channel_page = parse_hex_bytes(chp)[0];
-            }
+    let pos = line.iter().position(|b| *b == b':')?;
+    let key = String::from_utf8_lossy(&line[..pos]).to_string();
 
-            if let Some(pid) = line.strip_prefix(b"Pan ID:") {
-                desc.pan_id = BigEndian::read_u16(&parse_hex_bytes(pid));
-            }
+    if indented || KEYS.contains(&key.as_str()) {
+        Some((key, &line[pos + 1..]))
+    } else {
+        None
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn receive_epandesc_until(
+        &mut self,
+        deadline: Option<I::Instant>,
+    ) -> Result<EPanDesc> {
+        let mut desc = EPanDesc::default();
+
+        loop {
+            let line = self.receive_frame_until(deadline).await?;
+
+            debug!("< {}", String::from_utf8_lossy(&line));
 
-            if let Some(addr) = line.strip_prefix(b"Addr:") {
-                desc.addr = parse_hex_bytes(addr).try_into().unwrap();
+            match apply_line(&mut desc, &line)? {
+                Some(true) => break,
+                Some(false) => {}
+                None => {
+                    // EPANDESC の後に続いていた別の応答なので、順序を保って読み戻す
+                    self.buf.push_front(Payload::from(line));
+                    break;
+                }
             }
         }
 
         Ok(desc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::event::epandesc::split_field;
+
+    #[test]
+    fn test_split_field() {
+        assert_eq!(
+            Some(("Channel Page".to_string(), b"09".as_slice())),
+            split_field(b"  Channel Page:09"),
+        );
+        assert_eq!(
+            Some(("LQI".to_string(), b"E1".as_slice())),
+            split_field(b"LQI:E1"),
+        );
+        assert_eq!(
+            None,
+            split_field(b"EVENT 22 FE80:0000:0000:0000:021D:1290:1234:5678 0")
+        );
+    }
+}
@@ -0,0 +1,29 @@
+use core::net::Ipv6Addr;
+
+use crate::{Error, Result};
+use crate::payload::Payload;
+use crate::utils::{arg, parse_flag, parse_ipv6};
+
+#[cfg(feature = "std")]
+pub(crate) const EPONG: &[u8] = b"EPONG";
+
+/// SKPING で送信した Echo request に対する Echo reply の受信通知
+#[derive(Clone, Debug)]
+pub struct EPong {
+    pub sender: Ipv6Addr,
+    pub side: u8,
+}
+
+impl TryFrom<&Payload> for EPong {
+    type Error = Error;
+
+    fn try_from(value: &Payload) -> Result<Self> {
+        Ok(Self {
+            sender: parse_ipv6("sender", arg(value, 0, "sender")?)?,
+            side: match value.args.get(1) {
+                Some(s) => parse_flag("side", s)? as u8,
+                _ => 0,
+            },
+        })
+    }
+}
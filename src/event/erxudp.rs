@@ -0,0 +1,130 @@
+use alloc::vec::Vec;
+use core::net::Ipv6Addr;
+
+use tracing::warn;
+
+use crate::{Error, Result, WaitMap};
+use crate::payload::Payload;
+use crate::protocol::{Io, Protocol};
+use crate::utils::{
+    arg, parse_flag, parse_hex_array, parse_hex_u16, parse_ipv6, try_parse_hex_bytes,
+};
+
+pub(crate) const ERXUDP: &[u8] = b"ERXUDP";
+
+#[derive(Clone, Debug)]
+pub struct ERxUdp {
+    pub sender: Ipv6Addr,
+    pub dest: Ipv6Addr,
+    pub rport: u16,
+    pub lport: u16,
+    pub sender_lla: [u8; 8],
+    pub secured: bool,
+    pub side: u8,
+    pub data_len: u16,
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<&Payload> for ERxUdp {
+    type Error = Error;
+
+    fn try_from(value: &Payload) -> Result<Self> {
+        let data_len = parse_hex_u16("data_len", arg(value, 7, "data_len")?)?;
+
+        let data = value.data.clone().unwrap_or_default();
+
+        // ASCII 形式で出力された場合は 16 進文字列としてデコードする
+        let data = if data.len() == data_len as usize * 2 && data.iter().all(u8::is_ascii_hexdigit)
+        {
+            try_parse_hex_bytes("data", &data)?
+        } else {
+            data
+        };
+
+        Ok(Self {
+            sender: parse_ipv6("sender", arg(value, 0, "sender")?)?,
+            dest: parse_ipv6("dest", arg(value, 1, "dest")?)?,
+            rport: parse_hex_u16("rport", arg(value, 2, "rport")?)?,
+            lport: parse_hex_u16("lport", arg(value, 3, "lport")?)?,
+            sender_lla: parse_hex_array("sender_lla", arg(value, 4, "sender_lla")?)?,
+            secured: parse_flag("secured", arg(value, 5, "secured")?)?,
+            side: parse_flag("side", arg(value, 6, "side")?)? as u8,
+            data_len,
+            data,
+        })
+    }
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn receive_udp(&mut self) -> Result<ERxUdp> {
+        self.wait_for_udp(|_| true).await
+    }
+
+    pub(crate) async fn wait_for_udp<F>(&mut self, criteria: F) -> Result<ERxUdp>
+    where
+        F: Fn(&ERxUdp) -> bool,
+    {
+        self.wait_map_until(None, |p| {
+            if p.name == ERXUDP {
+                match ERxUdp::try_from(&p) {
+                    Ok(udp) if criteria(&udp) => return WaitMap::Finish(udp),
+                    Err(e) => {
+                        warn!("Ignoring malformed ERXUDP: {e}");
+                        return WaitMap::Consume;
+                    }
+                    _ => {}
+                }
+            }
+
+            WaitMap::Continue(p)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::Bp35c0;
+    use crate::event::erxudp::ERxUdp;
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+    use crate::payload::Payload;
+
+    #[test]
+    fn test_decode() {
+        let payload = Payload::from(
+            b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:1234:9ABC 0E1A 0E1A 001D129012345678 1 0 0004 a b ".to_vec(),
+        );
+        let udp = ERxUdp::try_from(&payload).unwrap();
+
+        assert_eq!(0x0E1A, udp.rport);
+        assert_eq!(
+            [0x00, 0x1D, 0x12, 0x90, 0x12, 0x34, 0x56, 0x78],
+            udp.sender_lla
+        );
+        assert!(udp.secured);
+        assert_eq!(b"a b ".to_vec(), udp.data);
+
+        let payload = Payload::from(
+            b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:1234:9ABC 0E1A 0E1A 001D129012345678 0 0 0002 1081".to_vec(),
+        );
+        let udp = ERxUdp::try_from(&payload).unwrap();
+
+        assert_eq!(vec![0x10, 0x81], udp.data);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_receive_binary() {
+        let mock = MockBp35c0::new();
+        let mut device = Bp35c0::connect(mock.clone()).unwrap();
+
+        // バイナリ形式の DATA に含まれる CRLF で応答を区切らない
+        mock.push_line(b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:1234:9ABC 0E1A 0E1A 001D129012345678 1 0 0004 \r\n a");
+        mock.push_line(b"EVENT 21 FE80:0000:0000:0000:021D:1290:1234:5678 0 00");
+
+        assert_eq!(b"\r\n a".to_vec(), device.receive_udp().unwrap().data);
+        assert!(device.wait_for_event(|_| true).is_ok());
+    }
+}
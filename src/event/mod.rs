@@ -1,14 +1,17 @@
-use std::net::Ipv6Addr;
-use std::str::FromStr;
+use alloc::vec::Vec;
+use core::net::Ipv6Addr;
 
+use crate::{Error, Result};
 use crate::payload::Payload;
-use crate::utils::parse_hex_bytes;
+use crate::utils::{arg, parse_flag, parse_hex_u8, parse_ipv6};
 
+pub mod eedscan;
 pub mod epandesc;
+pub mod epong;
+pub mod erxudp;
 
 pub const EVENT: &[u8] = b"EVENT";
 
-#[allow(dead_code)]
 #[repr(u8)]
 #[derive(Copy, Clone, Debug)]
 enum EventType {
@@ -39,6 +42,39 @@ enum EventType {
     InitialSetupFinished = 0x57,
 }
 
+impl EventType {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0x01 => Self::NSReceived,
+            0x02 => Self::NAReceived,
+            0x05 => Self::EchoRequest,
+            0x1F => Self::EDScanFinished,
+            0x20 => Self::Beacon,
+            0x21 => Self::UDPSendFinished,
+            0x22 => Self::ActiveScanFinished,
+            0x24 => Self::PanaError,
+            0x25 => Self::PanaConnected,
+            0x26 => Self::PanaTerminationRequest,
+            0x27 => Self::PanaTerminated,
+            0x28 => Self::PanaTerminationTimeout,
+            0x29 => Self::PanaTimedOut,
+            0x32 => Self::Arib108QuotaExceeded,
+            0x33 => Self::Arib108QuotaRecovered,
+            0x45 => Self::InvalidCipherReceived,
+            0x46 => Self::KeyUpdateTimedOut,
+            0x50 => Self::KeyUpdateRequested,
+            0x51 => Self::KeyUpdateResponse,
+            0x52 => Self::KeyUpdateNoResponse,
+            0x53 => Self::KeyRequest,
+            0x54 => Self::KeyDistributionStarted,
+            0x55 => Self::KeyDistributionFinished,
+            0x56 => Self::InitialSetupStarted,
+            0x57 => Self::InitialSetupFinished,
+            _ => return None,
+        })
+    }
+}
+
 pub struct RawEvent {
     pub num: u8,
     pub sender: Ipv6Addr,
@@ -46,13 +82,24 @@ pub struct RawEvent {
     pub param: Option<Vec<u8>>,
 }
 
-impl From<&Payload> for RawEvent {
-    fn from(value: &Payload) -> Self {
-        Self {
-            num: parse_hex_bytes(&value.args[0])[0],
-            sender: Ipv6Addr::from_str(&String::from_utf8_lossy(&value.args[1])).unwrap(),
-            side: if value.args[2][0] == b'1' { 1 } else { 0 },
+impl TryFrom<&Payload> for RawEvent {
+    type Error = Error;
+
+    fn try_from(value: &Payload) -> Result<Self> {
+        Ok(Self {
+            num: parse_hex_u8("num", arg(value, 0, "num")?)?,
+            sender: parse_ipv6("sender", arg(value, 1, "sender")?)?,
+            side: parse_flag("side", arg(value, 2, "side")?)? as u8,
             param: value.args.get(3).map(|a| a.to_vec()),
+        })
+    }
+}
+
+impl RawEvent {
+    fn param_u8(&self) -> Result<u8> {
+        match &self.param {
+            Some(p) => parse_hex_u8("param", p),
+            _ => Err(Error::parse("param", &[])),
         }
     }
 }
@@ -65,14 +112,34 @@ pub enum UDPSendResult {
     NSDispatched = 2,
 }
 
+impl TryFrom<u8> for UDPSendResult {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Success),
+            1 => Ok(Self::Failure),
+            2 => Ok(Self::NSDispatched),
+            v => Err(Error::parse("result", &[v])),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum EventBody {
     NSReceived,
     NAReceived,
+
+    /// 他の端末から ICMP Echo request を受信した (EVENT 05)
+    ///
+    /// SKPING で送信した Echo request への応答ではありません。応答の Echo reply は EPONG で通知されます。
     EchoRequest,
+
     EDScanFinished,
     Beacon,
-    UDPSendFinished { result: UDPSendResult },
+    UDPSendFinished {
+        result: UDPSendResult,
+    },
     ActiveScanFinished,
     PanaError,
     PanaConnected,
@@ -82,7 +149,9 @@ pub enum EventBody {
     PanaTimedOut,
     Arib108QuotaExceeded,
     Arib108QuotaRecovered,
-    InvalidCipherReceived { actual: u8 },
+    InvalidCipherReceived {
+        actual: u8,
+    },
     KeyUpdateTimedOut,
     KeyUpdateRequested,
     KeyUpdateResponse,
@@ -92,18 +161,35 @@ pub enum EventBody {
     KeyDistributionFinished,
     InitialSetupStarted,
     InitialSetupFinished,
+
+    Unknown {
+        num: u8,
+        param: Option<Vec<u8>>,
+    },
 }
 
-impl From<&RawEvent> for EventBody {
-    fn from(value: &RawEvent) -> Self {
-        match unsafe { std::mem::transmute::<u8, EventType>(value.num) } {
+impl TryFrom<&RawEvent> for EventBody {
+    type Error = Error;
+
+    fn try_from(value: &RawEvent) -> Result<Self> {
+        let event_type = match EventType::from_u8(value.num) {
+            Some(t) => t,
+            _ => {
+                return Ok(Self::Unknown {
+                    num: value.num,
+                    param: value.param.clone(),
+                });
+            }
+        };
+
+        let body = match event_type {
             EventType::NSReceived => Self::NSReceived,
             EventType::NAReceived => Self::NAReceived,
             EventType::EchoRequest => Self::EchoRequest,
             EventType::EDScanFinished => Self::EDScanFinished,
             EventType::Beacon => Self::Beacon,
             EventType::UDPSendFinished => Self::UDPSendFinished {
-                result: unsafe { std::mem::transmute(value.param.as_ref().unwrap()[0]) },
+                result: UDPSendResult::try_from(value.param_u8()?)?,
             },
             EventType::ActiveScanFinished => Self::ActiveScanFinished,
             EventType::PanaError => Self::PanaError,
@@ -115,7 +201,7 @@ impl From<&RawEvent> for EventBody {
             EventType::Arib108QuotaExceeded => Self::Arib108QuotaExceeded,
             EventType::Arib108QuotaRecovered => Self::Arib108QuotaRecovered,
             EventType::InvalidCipherReceived => Self::InvalidCipherReceived {
-                actual: value.param.as_ref().unwrap()[0],
+                actual: value.param_u8()?,
             },
             EventType::KeyUpdateTimedOut => Self::KeyUpdateTimedOut,
             EventType::KeyUpdateRequested => Self::KeyUpdateRequested,
@@ -126,28 +212,76 @@ impl From<&RawEvent> for EventBody {
             EventType::KeyDistributionFinished => Self::KeyDistributionFinished,
             EventType::InitialSetupStarted => Self::InitialSetupStarted,
             EventType::InitialSetupFinished => Self::InitialSetupFinished,
-        }
+        };
+
+        Ok(body)
     }
 }
 
+#[derive(Clone, Debug)]
 pub struct Header {
     pub sender: Ipv6Addr,
     pub side: u8,
 }
 
+#[derive(Clone, Debug)]
 pub struct Event {
     pub header: Header,
     pub body: EventBody,
 }
 
-impl From<&RawEvent> for Event {
-    fn from(value: &RawEvent) -> Self {
-        Self {
+impl TryFrom<&RawEvent> for Event {
+    type Error = Error;
+
+    fn try_from(value: &RawEvent) -> Result<Self> {
+        Ok(Self {
             header: Header {
                 sender: value.sender,
                 side: value.side,
             },
-            body: value.into(),
-        }
+            body: value.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<&Payload> for Event {
+    type Error = Error;
+
+    fn try_from(value: &Payload) -> Result<Self> {
+        Self::try_from(&RawEvent::try_from(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::event::{Event, EventBody, UDPSendResult};
+    use crate::payload::Payload;
+
+    #[test]
+    fn test_event() {
+        let payload =
+            Payload::from(b"EVENT 21 FE80:0000:0000:0000:021D:1290:1234:5678 0 02".to_vec());
+        assert!(matches!(
+            Event::try_from(&payload).unwrap().body,
+            EventBody::UDPSendFinished {
+                result: UDPSendResult::NSDispatched
+            },
+        ));
+
+        let payload =
+            Payload::from(b"EVENT 21 FE80:0000:0000:0000:021D:1290:1234:5678 0 03".to_vec());
+        assert!(Event::try_from(&payload).is_err());
+
+        let payload = Payload::from(b"EVENT 05 FE80:0000:0000:0000:021D:1290:1234:5678 0".to_vec());
+        let event = Event::try_from(&payload).unwrap();
+        assert!(matches!(event.body, EventBody::EchoRequest));
+        assert_eq!("fe80::21d:1290:1234:5678".parse(), Ok(event.header.sender));
+
+        let payload =
+            Payload::from(b"EVENT 99 FE80:0000:0000:0000:021D:1290:1234:5678 0 AB".to_vec());
+        assert!(matches!(
+            Event::try_from(&payload).unwrap().body,
+            EventBody::Unknown { num: 0x99, param: Some(p) } if p == b"AB",
+        ));
     }
 }
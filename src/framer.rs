@@ -0,0 +1,170 @@
+//! 受信したバイト列を 1 件ずつの応答（フレーム）に区切る処理
+//!
+//! 応答は基本的に CRLF で終わる 1 行ですが、ERXUDP の DATA はバイナリ形式で出力されると空白や CR, LF を含み得ます。
+//! そのような応答はヘッダの DATALEN を読み取り、そのバイト数だけ末尾のデータとして読み込みます。
+
+use alloc::vec::Vec;
+
+use crate::{CRLF, LF, strip_crlf};
+use crate::event::erxudp::ERXUDP;
+
+/// バイナリの末尾を持ち得る応答と、末尾の前に並ぶ引数の数（最後の引数が末尾のバイト数を表す）
+const BINARY_TAILS: &[(&[u8], usize)] = &[(ERXUDP, 8)];
+
+const SPACE: u8 = b' ';
+
+/// 末尾のデータの前に並ぶ引数の数を返します。バイナリの末尾を持たない応答であれば `None` を返します。
+pub(crate) fn binary_tail(name: &[u8]) -> Option<usize> {
+    BINARY_TAILS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, args)| *args)
+}
+
+enum Tail {
+    /// バイナリの末尾を持たないので、行末までが 1 フレーム
+    None,
+
+    /// ヘッダをまだ受信しきっていない
+    Incomplete,
+
+    /// `start` から `len` バイト（16 進形式であれば `len * 2` 文字）が末尾のデータ
+    Data { start: usize, len: usize },
+}
+
+#[derive(Default)]
+pub(crate) struct Framer {
+    buf: Vec<u8>,
+}
+
+impl Framer {
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn clear(&mut self) {
+        self.buf.clear();
+    }
+
+    /// 受信しきったフレームがあれば、末尾の CRLF を取り除いて取り出します。
+    pub(crate) fn next_frame(&mut self) -> Option<Vec<u8>> {
+        let len = self.frame_len()?;
+        let rest = self.buf.split_off(len);
+
+        Some(strip_crlf(core::mem::replace(&mut self.buf, rest)))
+    }
+
+    /// 先頭のフレームの CRLF を含む長さを返します。
+    fn frame_len(&self) -> Option<usize> {
+        let (start, len) = match self.tail() {
+            Tail::None => return self.line_len(0),
+            Tail::Incomplete => return None,
+            Tail::Data { start, len } => (start, len),
+        };
+
+        // バイナリ形式であれば DATALEN バイトの直後が、16 進形式であれば DATALEN * 2 文字の直後が CRLF になる
+        // 16 進形式の DATA は CR や LF を含まないので、先にバイナリ形式として判定しても取り違えない
+        for end in [start + len, start + len * 2] {
+            match self.buf.get(end..end + CRLF.len()) {
+                Some(crlf) if crlf == CRLF => return Some(end + CRLF.len()),
+                Some(_) => {}
+                None => return None,
+            }
+        }
+
+        // どちらの長さとも合わない場合は、DATA の後の最初の行末までを 1 フレームとみなす
+        self.line_len(start)
+    }
+
+    fn line_len(&self, from: usize) -> Option<usize> {
+        self.buf[from..]
+            .iter()
+            .position(|b| *b == LF)
+            .map(|pos| from + pos + 1)
+    }
+
+    fn tail(&self) -> Tail {
+        let mut args = None;
+        let mut spaces = 0;
+        let mut token = 0;
+
+        for (i, b) in self.buf.iter().enumerate() {
+            match *b {
+                LF => return Tail::None,
+                SPACE => {}
+                _ => continue,
+            }
+
+            if spaces == 0 {
+                args = binary_tail(&self.buf[..i]);
+                if args.is_none() {
+                    return Tail::None;
+                }
+            } else if args == Some(spaces) {
+                // 最後の引数（DATALEN）まで読み終えた
+                return match parse_len(&self.buf[token..i]) {
+                    Some(len) => Tail::Data { start: i + 1, len },
+                    _ => Tail::None,
+                };
+            }
+
+            spaces += 1;
+            token = i + 1;
+        }
+
+        Tail::Incomplete
+    }
+}
+
+fn parse_len(bytes: &[u8]) -> Option<usize> {
+    let str = core::str::from_utf8(bytes).ok()?;
+    usize::from_str_radix(str, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framer::Framer;
+
+    const HEADER: &[u8] = b"ERXUDP FE80:0000:0000:0000:021D:1290:1234:5678 FE80:0000:0000:0000:021D:1290:1234:9ABC 0E1A 0E1A 001D129012345678 1 0 0005 ";
+
+    #[test]
+    fn test_lines() {
+        let mut framer = Framer::default();
+
+        framer.push(b"OK\r\nEVER 1.2");
+        assert_eq!(Some(b"OK".to_vec()), framer.next_frame());
+        assert_eq!(None, framer.next_frame());
+
+        framer.push(b".10\r\n");
+        assert_eq!(Some(b"EVER 1.2.10".to_vec()), framer.next_frame());
+        assert_eq!(None, framer.next_frame());
+    }
+
+    #[test]
+    fn test_binary_tail() {
+        let mut framer = Framer::default();
+
+        // DATA に空白や CRLF を含むバイナリ形式の ERXUDP を分割して受信する
+        framer.push(&HEADER[..20]);
+        assert_eq!(None, framer.next_frame());
+        framer.push(&HEADER[20..]);
+        framer.push(b"a \r\n");
+        assert_eq!(None, framer.next_frame());
+        framer.push(b"b\r\nOK\r\n");
+
+        let mut expected = HEADER.to_vec();
+        expected.extend_from_slice(b"a \r\nb");
+        assert_eq!(Some(expected), framer.next_frame());
+        assert_eq!(Some(b"OK".to_vec()), framer.next_frame());
+
+        // 16 進形式の ERXUDP
+        framer.push(HEADER);
+        framer.push(b"48656C6C6F\r\n");
+
+        let mut expected = HEADER.to_vec();
+        expected.extend_from_slice(b"48656C6C6F");
+        assert_eq!(Some(expected), framer.next_frame());
+        assert_eq!(None, framer.next_frame());
+    }
+}
@@ -0,0 +1,740 @@
+//! テスト用に BP35C0 の振る舞いを模倣する通信路
+//!
+//! [`MockBp35c0`] は [`crate::Transport`] を実装しているので、実機の代わりに [`crate::Bp35c0::connect`] へ渡せます。
+//! ハンドルは複製でき、接続後もテストコードから応答の設定や失敗の注入、受信したコマンドの確認ができます。
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{ErrorKind, Read, Write};
+use std::net::Ipv6Addr;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::event::eedscan::EnergyLevel;
+use crate::event::epandesc::EPanDesc;
+use crate::utils::{
+    ipv6_to_hex_bytes, itoa, parse_hex_array, parse_hex_u16, to_hex_bytes, u16_to_hex_bytes,
+};
+
+const CRLF: &[u8] = b"\r\n";
+
+struct State {
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+    received: Vec<Vec<u8>>,
+    datagrams: Vec<(Ipv6Addr, u16, Vec<u8>)>,
+    registers: BTreeMap<Vec<u8>, Vec<u8>>,
+    saved_registers: Option<BTreeMap<Vec<u8>, Vec<u8>>>,
+    failures: HashMap<Vec<u8>, VecDeque<u8>>,
+    version: Vec<u8>,
+    ip_addr: Ipv6Addr,
+    addr_64: [u8; 8],
+    pans: Vec<EPanDesc>,
+    energy_levels: Vec<EnergyLevel>,
+    join_results: VecDeque<bool>,
+    terminate_results: VecDeque<bool>,
+    peer: Option<Ipv6Addr>,
+    rbid: Option<Vec<u8>>,
+    pwd: Option<Vec<u8>>,
+}
+
+/// BP35C0 のモック
+#[derive(Clone)]
+pub struct MockBp35c0 {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for MockBp35c0 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// テストで使う PAN の記述子（コーディネータのアドレスは FE80::021D:1290:0000:0002）
+#[cfg(test)]
+pub(crate) fn test_pan() -> EPanDesc {
+    EPanDesc {
+        channel: 0x3B,
+        channel_page: 0x09,
+        pan_id: 0x1234,
+        addr: [0x00, 0x1D, 0x12, 0x90, 0x00, 0x00, 0x00, 0x02],
+        lqi: 0xE1,
+        side: 0,
+        pair_id: None,
+        extras: BTreeMap::new(),
+    }
+}
+
+impl MockBp35c0 {
+    pub fn new() -> Self {
+        let addr_64 = [0x00, 0x1D, 0x12, 0x90, 0x00, 0x00, 0x00, 0x01];
+
+        Self {
+            state: Arc::new(Mutex::new(State {
+                input: Vec::new(),
+                output: VecDeque::new(),
+                received: Vec::new(),
+                datagrams: Vec::new(),
+                registers: default_registers(),
+                saved_registers: None,
+                failures: HashMap::new(),
+                version: b"1.0.0".to_vec(),
+                ip_addr: link_local(&addr_64),
+                addr_64,
+                pans: Vec::new(),
+                energy_levels: Vec::new(),
+                join_results: VecDeque::new(),
+                terminate_results: VecDeque::new(),
+                peer: None,
+                rbid: None,
+                pwd: None,
+            })),
+        }
+    }
+
+    /// [`test_pan`] がアクティブスキャンで見つかるモックを作成します。
+    #[cfg(test)]
+    pub(crate) fn with_test_pan() -> Self {
+        let mock = Self::new();
+        mock.add_pan(test_pan());
+        mock
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// SKVER で返すバージョンを設定します。
+    pub fn set_version(&self, version: &str) {
+        self.lock().version = version.as_bytes().to_vec();
+    }
+
+    /// アクティブスキャンで見つかる PAN を追加します。
+    pub fn add_pan(&self, desc: EPanDesc) {
+        self.lock().pans.push(desc);
+    }
+
+    /// ED スキャンで返すエネルギーレベルを設定します。
+    pub fn set_energy_levels(&self, levels: Vec<EnergyLevel>) {
+        self.lock().energy_levels = levels;
+    }
+
+    /// 次の SKJOIN または SKREJOIN の結果を追加します。設定されていない場合は成功します。
+    pub fn push_join_result(&self, connected: bool) {
+        self.lock().join_results.push_back(connected);
+    }
+
+    /// 次の SKTERM で相手が終了要求に応答するかどうかを追加します。設定されていない場合は応答します。
+    pub fn push_terminate_result(&self, responded: bool) {
+        self.lock().terminate_results.push_back(responded);
+    }
+
+    /// 次に `name` のコマンドを受信したとき、`FAIL ERxx` を返すようにします。
+    pub fn fail_next(&self, name: &[u8], code: u8) {
+        self.lock()
+            .failures
+            .entry(name.to_vec())
+            .or_default()
+            .push_back(code);
+    }
+
+    /// 任意の行（EVENT や ERXUDP など）を出力します。
+    pub fn push_line(&self, line: &[u8]) {
+        let mut state = self.lock();
+        state.output.extend(line);
+        state.output.extend(CRLF);
+    }
+
+    /// 受信したコマンドを改行を除いて返します。
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        self.lock().received.clone()
+    }
+
+    /// SKSENDTO で送信された UDP データグラムを、宛先のアドレスとポートとともに返します。
+    pub fn datagrams(&self) -> Vec<(Ipv6Addr, u16, Vec<u8>)> {
+        self.lock().datagrams.clone()
+    }
+
+    /// レジスタの値を返します。`register` には `S02` のような名前を指定します。
+    pub fn register(&self, register: &[u8]) -> Option<Vec<u8>> {
+        self.lock().registers.get(register).cloned()
+    }
+
+    /// SKSAVE でフラッシュメモリに保存されたレジスタの値を返します。
+    pub fn saved_register(&self, register: &[u8]) -> Option<Vec<u8>> {
+        self.lock()
+            .saved_registers
+            .as_ref()
+            .and_then(|r| r.get(register).cloned())
+    }
+
+    pub fn rbid(&self) -> Option<Vec<u8>> {
+        self.lock().rbid.clone()
+    }
+
+    pub fn pwd(&self) -> Option<Vec<u8>> {
+        self.lock().pwd.clone()
+    }
+
+    /// 自端末の IPv6 アドレス
+    pub fn ip_addr(&self) -> Ipv6Addr {
+        self.lock().ip_addr
+    }
+}
+
+impl Read for MockBp35c0 {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut state = self.lock();
+        if state.output.is_empty() {
+            // 実機のシリアルポートと同じく、データが無ければ読み込みタイムアウトとして扱う
+            return Err(ErrorKind::TimedOut.into());
+        }
+
+        let len = buf.len().min(state.output.len());
+        for (dst, src) in buf.iter_mut().zip(state.output.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
+    }
+}
+
+impl Write for MockBp35c0 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.lock();
+        state.input.extend_from_slice(buf);
+
+        while let Some(len) = command_len(&state.input) {
+            let command = state.input.drain(..len).collect::<Vec<_>>();
+            state.handle(&command[..command.len() - CRLF.len()]);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 入力されたバイト列の先頭にある完結したコマンドの長さを CRLF 込みで返します。
+/// SKSENDTO は DATA 部に CRLF を含み得るため、DATALEN に従って長さを求めます。
+fn command_len(input: &[u8]) -> Option<usize> {
+    if input.starts_with(b"SKSENDTO ") {
+        let mut spaces = input
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b' ')
+            .map(|(i, _)| i);
+
+        let len_start = spaces.nth(5)? + 1;
+        let data_start = spaces.next()? + 1;
+        let data_len = parse_hex_u16("data_len", &input[len_start..data_start - 1]).ok()?;
+        let end = data_start + data_len as usize + CRLF.len();
+
+        return (input.len() >= end).then_some(end);
+    }
+
+    input
+        .windows(CRLF.len())
+        .position(|w| w == CRLF)
+        .map(|p| p + CRLF.len())
+}
+
+fn default_registers() -> BTreeMap<Vec<u8>, Vec<u8>> {
+    [
+        (b"S02".to_vec(), b"21".to_vec()),
+        (b"S03".to_vec(), b"FFFF".to_vec()),
+        (b"S07".to_vec(), b"00000000".to_vec()),
+        (b"S15".to_vec(), b"1".to_vec()),
+        (b"S16".to_vec(), b"00000E10".to_vec()),
+        (b"S17".to_vec(), b"1".to_vec()),
+        (b"SA1".to_vec(), b"1".to_vec()),
+        (b"SF0".to_vec(), b"0".to_vec()),
+        (b"SFB".to_vec(), b"0".to_vec()),
+        (b"SFD".to_vec(), b"00000000".to_vec()),
+        (b"SFE".to_vec(), b"1".to_vec()),
+        (b"SFF".to_vec(), b"0".to_vec()),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// コマンドの引数に書かれた IPv6 アドレスを変換します。
+fn parse_ipv6(src: &[u8]) -> Option<Ipv6Addr> {
+    std::str::from_utf8(src).ok()?.parse().ok()
+}
+
+/// MAC アドレスから IPv6 リンクローカルアドレスを求めます。
+fn link_local(addr_64: &[u8; 8]) -> Ipv6Addr {
+    let mut octets = [0u8; 16];
+    octets[0] = 0xFE;
+    octets[1] = 0x80;
+    octets[8..].copy_from_slice(addr_64);
+    octets[8] ^= 0x02;
+    Ipv6Addr::from(octets)
+}
+
+impl State {
+    fn line(&mut self, line: &[u8]) {
+        self.output.extend(line);
+        self.output.extend(CRLF);
+    }
+
+    fn ok(&mut self) {
+        self.line(b"OK");
+    }
+
+    fn event(&mut self, num: u8, sender: &Ipv6Addr, param: Option<&[u8]>) {
+        let mut line = b"EVENT ".to_vec();
+        line.extend(itoa(num));
+        line.push(b' ');
+        line.extend(ipv6_to_hex_bytes(sender));
+        line.extend(b" 0");
+        if let Some(param) = param {
+            line.push(b' ');
+            line.extend(param);
+        }
+
+        self.line(&line);
+    }
+
+    fn handle(&mut self, command: &[u8]) {
+        self.received.push(command.to_vec());
+
+        if self.registers.get(b"SFE".as_slice()).map(Vec::as_slice) == Some(b"1") {
+            self.line(command);
+        }
+
+        let mut parts = command.split(|b| *b == b' ');
+        let name = parts.next().unwrap_or_default().to_vec();
+        let args = parts.map(<[u8]>::to_vec).collect::<Vec<_>>();
+
+        if name.is_empty() {
+            return;
+        }
+
+        if let Some(code) = self.failures.get_mut(&name).and_then(VecDeque::pop_front) {
+            let mut line = b"FAIL ER".to_vec();
+            line.extend(format!("{code:02}").as_bytes());
+            self.line(&line);
+            return;
+        }
+
+        match name.as_slice() {
+            b"SKRESET" => {
+                self.registers.insert(b"SFE".to_vec(), b"1".to_vec());
+                self.ok();
+            }
+            b"SKSREG" => self.handle_sksreg(&args),
+            b"SKINFO" => self.handle_skinfo(),
+            b"SKVER" => {
+                let mut line = b"EVER ".to_vec();
+                line.extend(self.version.clone());
+                self.line(&line);
+                self.ok();
+            }
+            b"SKSCAN" => self.handle_skscan(&args),
+            b"SKJOIN" => match args.first().and_then(|a| parse_ipv6(a)) {
+                Some(ip_addr) => {
+                    self.ok();
+                    self.join(ip_addr);
+                }
+                _ => self.line(b"FAIL ER04"),
+            },
+            b"SKREJOIN" => match self.peer {
+                Some(ip_addr) => {
+                    self.ok();
+                    self.join(ip_addr);
+                }
+                _ => self.line(b"FAIL ER10"),
+            },
+            b"SKTERM" => match self.peer.take() {
+                Some(ip_addr) => {
+                    self.ok();
+                    self.terminate(ip_addr);
+                }
+                _ => self.line(b"FAIL ER10"),
+            },
+            b"SKLL64" => match args.first().map(|a| parse_hex_array::<8>("addr_64", a)) {
+                Some(Ok(addr_64)) => self.line(&ipv6_to_hex_bytes(&link_local(&addr_64))),
+                _ => self.line(b"FAIL ER04"),
+            },
+            b"SKSETPWD" => match args.as_slice() {
+                [_, pwd @ ..] if !pwd.is_empty() => {
+                    self.pwd = Some(pwd.join(&b' '));
+                    self.ok();
+                }
+                _ => self.line(b"FAIL ER04"),
+            },
+            b"SKSETRBID" => match args.first() {
+                Some(rbid) => {
+                    self.rbid = Some(rbid.clone());
+                    self.ok();
+                }
+                _ => self.line(b"FAIL ER04"),
+            },
+            b"SKSENDTO" => self.handle_sksendto(command),
+            b"SKSAVE" => {
+                self.saved_registers = Some(self.registers.clone());
+                self.ok();
+            }
+            b"SKLOAD" => match self.saved_registers.clone() {
+                Some(registers) => {
+                    self.registers = registers;
+                    self.ok();
+                }
+                _ => self.line(b"FAIL ER10"),
+            },
+            b"SKERASE" => {
+                self.saved_registers = None;
+                self.ok();
+            }
+            _ => self.line(b"FAIL ER04"),
+        }
+    }
+
+    fn join(&mut self, ip_addr: Ipv6Addr) {
+        if self.join_results.pop_front().unwrap_or(true) {
+            self.peer = Some(ip_addr);
+            self.event(0x25, &ip_addr, None);
+        } else {
+            self.peer = None;
+            self.event(0x24, &ip_addr, None);
+        }
+    }
+
+    fn terminate(&mut self, ip_addr: Ipv6Addr) {
+        if self.terminate_results.pop_front().unwrap_or(true) {
+            self.event(0x27, &ip_addr, None);
+        } else {
+            self.event(0x28, &ip_addr, None);
+        }
+    }
+
+    fn handle_skinfo(&mut self) {
+        let channel = self.registers.get(b"S02".as_slice()).cloned();
+        let pan_id = self.registers.get(b"S03".as_slice()).cloned();
+        let (Some(channel), Some(pan_id)) = (channel, pan_id) else {
+            self.line(b"FAIL ER04");
+            return;
+        };
+
+        let mut line = b"EINFO ".to_vec();
+        line.extend(ipv6_to_hex_bytes(&self.ip_addr));
+        line.push(b' ');
+        line.extend(to_hex_bytes(&self.addr_64));
+        line.push(b' ');
+        line.extend(channel);
+        line.push(b' ');
+        line.extend(pan_id);
+        line.extend(b" 0");
+        self.line(&line);
+        self.ok();
+    }
+
+    fn handle_sksreg(&mut self, args: &[Vec<u8>]) {
+        match args {
+            [register] => match self.registers.get(register).cloned() {
+                Some(value) => {
+                    let mut line = b"ESREG ".to_vec();
+                    line.extend(value);
+                    self.line(&line);
+                    self.ok();
+                }
+                _ => self.line(b"FAIL ER06"),
+            },
+            [register, value] => {
+                self.registers.insert(register.clone(), value.clone());
+                self.ok();
+            }
+            _ => self.line(b"FAIL ER05"),
+        }
+    }
+
+    fn handle_sksendto(&mut self, command: &[u8]) {
+        // DATA 部は空白を含み得るので、引数の数を限って分割する
+        let parts = command.splitn(8, |b| *b == b' ').collect::<Vec<_>>();
+        let parsed = match parts.as_slice() {
+            [_, _, ip_addr, port, _, _, _, data] => parse_ipv6(ip_addr)
+                .zip(parse_hex_u16("port", port).ok())
+                .map(|(ip_addr, port)| (ip_addr, port, data.to_vec())),
+            _ => None,
+        };
+
+        let Some((ip_addr, port, data)) = parsed else {
+            self.line(b"FAIL ER04");
+            return;
+        };
+
+        // 実機と同じく、送信完了の EVENT 21 を OK より先に返す
+        self.datagrams.push((ip_addr, port, data));
+        self.event(0x21, &ip_addr, Some(b"00"));
+        self.ok();
+    }
+
+    fn handle_skscan(&mut self, args: &[Vec<u8>]) {
+        if args.len() != 4 {
+            self.line(b"FAIL ER05");
+            return;
+        }
+
+        self.ok();
+
+        let ip_addr = self.ip_addr;
+        match args[0].as_slice() {
+            b"0" => {
+                let levels = self
+                    .energy_levels
+                    .iter()
+                    .flat_map(|l| [itoa(l.channel).to_vec(), itoa(l.rssi).to_vec()])
+                    .collect::<Vec<_>>();
+
+                // 実機では EVENT 1F が EEDSCAN より先に届くことがある
+                self.event(0x1F, &ip_addr, None);
+                self.line(b"EEDSCAN");
+                self.line(&levels.join(&b' '));
+            }
+            _ => {
+                for desc in self.pans.clone() {
+                    self.line(b"EPANDESC");
+                    self.field(b"Channel", &itoa(desc.channel));
+                    self.field(b"Channel Page", &itoa(desc.channel_page));
+                    self.field(b"Pan ID", &u16_to_hex_bytes(desc.pan_id));
+                    self.field(b"Addr", &to_hex_bytes(&desc.addr));
+                    self.field(b"LQI", &itoa(desc.lqi));
+                    self.field(b"Side", &itoa(desc.side)[1..]);
+                    if let Some(pair_id) = desc.pair_id {
+                        self.field(b"PairID", &to_hex_bytes(&pair_id));
+                    }
+                }
+
+                self.event(0x22, &ip_addr, None);
+            }
+        }
+    }
+
+    fn field(&mut self, key: &[u8], value: &[u8]) {
+        let mut line = b"  ".to_vec();
+        line.extend(key);
+        line.push(b':');
+        line.extend(value);
+        self.line(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::Ipv6Addr;
+
+    use crate::{Bp35c0, Error, FailCode, FlashOperation};
+    use crate::cmd::{skjoin, sksendto};
+    use crate::cmd::sksreg::{Channel, Register, Value};
+    use crate::event::{EventBody, UDPSendResult};
+    use crate::event::eedscan::EnergyLevel;
+    use crate::event::epandesc::EPanDesc;
+    use crate::mock::{MockBp35c0, test_pan};
+
+    fn connect() -> (MockBp35c0, Bp35c0<MockBp35c0>) {
+        let mock = MockBp35c0::new();
+        let device = Bp35c0::connect(mock.clone()).unwrap();
+        (mock, device)
+    }
+
+    #[test]
+    fn test_connect() {
+        let (mock, _) = connect();
+
+        assert_eq!(Some(b"0".to_vec()), mock.register(b"SFE"));
+        assert_eq!(b"SKRESET".to_vec(), mock.received()[1]);
+    }
+
+    #[test]
+    fn test_version_and_info() {
+        let (mock, mut device) = connect();
+        mock.set_version("1.2.10");
+
+        assert_eq!("1.2.10", device.version().unwrap().version);
+
+        let info = device.info().unwrap();
+        assert_eq!(mock.ip_addr(), info.ip_addr);
+        assert_eq!(0x21, info.channel);
+        assert_eq!(0xFFFF, info.pan_id);
+    }
+
+    #[test]
+    fn test_credentials() {
+        let (mock, mut device) = connect();
+
+        device.set_rbid([0x30; 16]).unwrap();
+        device.set_pwd(b"PASSWORD").unwrap();
+
+        assert_eq!(Some(b"30".repeat(16)), mock.rbid());
+        assert_eq!(Some(b"PASSWORD".to_vec()), mock.pwd());
+    }
+
+    #[test]
+    fn test_scan_and_join() {
+        let (mock, mut device) = connect();
+        mock.add_pan(EPanDesc {
+            pair_id: Some([0x00, 0x11, 0x22, 0x33]),
+            ..test_pan()
+        });
+
+        let descs = device.scan_active(true, 0xFFFFFFFF, 6, 0).unwrap();
+        assert_eq!(1, descs.len());
+        assert_eq!(0x1234, descs[0].pan_id);
+        assert_eq!(0xE1, descs[0].lqi);
+        assert_eq!(Some([0x00, 0x11, 0x22, 0x33]), descs[0].pair_id);
+
+        let ip_addr = device.mac_to_ip_addr(descs[0].addr).unwrap().ip_addr;
+        assert_eq!("fe80::21d:1290:0:2".parse::<Ipv6Addr>().unwrap(), ip_addr);
+
+        mock.push_join_result(false);
+        assert_eq!(skjoin::Output::Failed, device.join(ip_addr).unwrap());
+        assert_eq!(skjoin::Output::Connected, device.join(ip_addr).unwrap());
+    }
+
+    #[test]
+    fn test_scan_energy() {
+        let (mock, mut device) = connect();
+        mock.set_energy_levels(vec![
+            EnergyLevel {
+                channel: 0x21,
+                rssi: 0x3C,
+            },
+            EnergyLevel {
+                channel: 0x22,
+                rssi: 0x42,
+            },
+        ]);
+
+        let levels = device.scan_energy(0x3, 4, 0).unwrap();
+        assert_eq!(2, levels.len());
+        assert_eq!(0x42, levels[1].rssi);
+    }
+
+    #[test]
+    fn test_send_to() {
+        let (mock, mut device) = connect();
+        let ip_addr = "fe80::21d:1290:0:2".parse::<Ipv6Addr>().unwrap();
+
+        let result = device
+            .send_to(
+                1,
+                ip_addr,
+                0x0E1A,
+                sksendto::Security::Encrypted,
+                0,
+                b"a b\r\n",
+            )
+            .unwrap();
+        assert!(matches!(result, UDPSendResult::Success));
+        assert_eq!(
+            vec![(ip_addr, 0x0E1A, b"a b\r\n".to_vec())],
+            mock.datagrams()
+        );
+
+        // FAIL の場合は EVENT 21 を待たずにエラーを返す
+        mock.fail_next(b"SKSENDTO", 6);
+        assert!(matches!(
+            device.send_to(1, ip_addr, 0x0E1A, sksendto::Security::Encrypted, 0, b"a"),
+            Err(Error::Fail(FailCode::ArgumentOutOfRange))
+        ));
+        assert_eq!(1, mock.datagrams().len());
+    }
+
+    #[test]
+    fn test_skip_malformed() {
+        let (mock, mut device) = connect();
+
+        // 条件に合わない不正な EVENT や ERXUDP は読み捨てて待機を続ける
+        mock.push_line(b"EVENT 21 FE80:0000:0000:0000:021D:1290:0000:0002 0 03");
+        mock.push_line(b"ERXUDP FE80:0000:0000:0000:021D:1290:0000:0002");
+        mock.push_line(b"EVENT 25 FE80:0000:0000:0000:021D:1290:0000:0002 0");
+        mock.push_line(b"ERXUDP FE80:0000:0000:0000:021D:1290:0000:0002 FE80:0000:0000:0000:021D:1290:0000:0001 0E1A 0E1A 001D129000000002 1 0 0001 a");
+
+        let event = device
+            .wait_for_event(|e| matches!(e.body, EventBody::PanaConnected))
+            .unwrap();
+        assert!(matches!(event.body, EventBody::PanaConnected));
+        assert_eq!(b"a".to_vec(), device.receive_udp().unwrap().data);
+    }
+
+    #[test]
+    fn test_failure() {
+        let (mock, mut device) = connect();
+        mock.fail_next(b"SKINFO", 10);
+
+        assert!(matches!(
+            device.info(),
+            Err(Error::Fail(FailCode::CommandFailed))
+        ));
+        assert!(device.info().is_ok());
+    }
+
+    #[test]
+    fn test_malformed_command() {
+        let (mut mock, _device) = connect();
+
+        for command in [
+            b"SKJOIN\r\n".as_slice(),
+            b"SKJOIN ::G\r\n",
+            b"SKLL64 001D\r\n",
+            b"SKSETPWD 8\r\n",
+            b"SKSETRBID\r\n",
+        ] {
+            mock.write_all(command).unwrap();
+
+            let mut output = Vec::new();
+            mock.read_to_end(&mut output).unwrap_err();
+            assert_eq!(b"FAIL ER04\r\n".to_vec(), output);
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_config() {
+        let (mock, mut device) = connect();
+        assert!(matches!(
+            device.load_config(),
+            Err(Error::Flash {
+                operation: FlashOperation::Load,
+                code: FailCode::CommandFailed,
+            })
+        ));
+
+        device.set_channel(Channel::new(0x3B).unwrap()).unwrap();
+        device.save_config().unwrap();
+        assert_eq!(Some(b"3B".to_vec()), mock.saved_register(b"S02"));
+
+        // 読み込みでエコーバックが無効に戻っても、続くコマンドの応答を受け取れる
+        device.set_channel(Channel::new(0x21).unwrap()).unwrap();
+        device
+            .raw()
+            .set_register(Register::SFE, Value::Bool(true))
+            .unwrap();
+        device.load_config().unwrap();
+        assert_eq!(Some(b"0".to_vec()), mock.register(b"SFE"));
+        assert_eq!(0x3B, device.channel().unwrap().get());
+
+        // ER10 以外のコードもそのまま返す
+        mock.fail_next(b"SKSAVE", 9);
+        assert!(matches!(
+            device.save_config(),
+            Err(Error::Flash {
+                operation: FlashOperation::Save,
+                code: FailCode::UartInputError,
+            })
+        ));
+        mock.fail_next(b"SKERASE", 10);
+        assert!(matches!(
+            device.erase_config(),
+            Err(Error::Flash {
+                operation: FlashOperation::Erase,
+                code: FailCode::CommandFailed,
+            })
+        ));
+
+        device.erase_config().unwrap();
+        assert_eq!(None, mock.saved_register(b"S02"));
+    }
+}
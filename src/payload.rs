@@ -1,16 +1,30 @@
-use std::fmt::{Debug, Formatter};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Formatter};
+
+use crate::framer::binary_tail;
 
 #[derive(Clone)]
 pub struct Payload {
     pub name: Vec<u8>,
     pub args: Vec<Vec<u8>>,
+    /// 引数の後ろに続くバイナリデータ（SKSENDTO の DATA 部など）
+    /// 区切り文字の空白を含み得るため、引数とは別に保持します。
+    pub data: Option<Vec<u8>>,
+}
+
+fn format_arg(arg: &[u8]) -> String {
+    match arg.is_ascii() {
+        true => String::from_utf8_lossy(arg).to_string(),
+        _ => hex::encode(arg),
+    }
 }
 
 impl Debug for Payload {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         let name = String::from_utf8_lossy(&self.name);
         if self.args.is_empty() {
-            write!(f, "{}", name)
+            write!(f, "{}", name)?;
         } else {
             write!(
                 f,
@@ -18,28 +32,41 @@ impl Debug for Payload {
                 name,
                 self.args
                     .iter()
-                    .map(|arg| match arg.is_ascii() {
-                        true => String::from_utf8_lossy(arg).to_string(),
-                        _ => hex::encode(arg),
-                    })
+                    .map(|arg| format_arg(arg))
                     .collect::<Vec<_>>()
                     .join(" "),
-            )
+            )?;
+        }
+
+        if let Some(data) = &self.data {
+            write!(f, " {}", format_arg(data))?;
         }
+
+        Ok(())
     }
 }
 
 impl From<Vec<u8>> for Payload {
     fn from(value: Vec<u8>) -> Self {
-        let parts = value
-            .split(|v| *v == 0x20)
+        let name_len = value.iter().position(|v| *v == 0x20).unwrap_or(value.len());
+
+        // バイナリの末尾を持つ応答は、ヘッダの引数だけを分割して残りをデータとする
+        let tail = binary_tail(&value[..name_len]);
+        let mut parts = value
+            .splitn(tail.map_or(usize::MAX, |args| args + 2), |v| *v == 0x20)
             .map(|v| v.to_vec())
             .collect::<Vec<_>>();
 
+        let data = match tail {
+            Some(args) if parts.len() == args + 2 => parts.pop(),
+            _ => None,
+        };
+
         if let Some((name, args)) = parts.split_first() {
             Self {
                 name: name.to_vec(),
                 args: args.to_vec(),
+                data,
             }
         } else {
             panic!("Illegal payload");
@@ -58,6 +85,11 @@ impl From<&Payload> for Vec<u8> {
             bytes.extend_from_slice(arg);
         });
 
+        if let Some(data) = &value.data {
+            bytes.push(0x20);
+            bytes.extend_from_slice(data);
+        }
+
         bytes
     }
 }
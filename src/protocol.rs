@@ -0,0 +1,436 @@
+//! 各ドライバに共通するプロトコルの処理
+//!
+//! コマンドの送信や応答の待機、エコーバックの判別といった状態を持つ処理は [`Protocol`] にまとめ、
+//! 通信路の読み書きと期限の管理だけを [`Io`] としてドライバごとに実装します。
+//! 処理は `async fn` で書かれており、非同期版のドライバはそのまま待機し、同期版と `no_std` 版のドライバは
+//! 決して中断しない通信路で [`block_on`] します。
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::{CRLF, Error, FailCode, OK, Result, WaitMap};
+use crate::cmd::*;
+use crate::echo::EchoBack;
+use crate::error::FAIL;
+use crate::event::{Event, EVENT};
+use crate::framer::Framer;
+use crate::payload::Payload;
+
+/// ドライバごとの通信路の読み書きと期限の管理
+pub(crate) trait Io {
+    /// 期限を表す時刻
+    type Instant: Copy;
+
+    /// 現在から `timeout` 後の期限を返します。時計が無く期限を管理できない場合は `None` を返します。
+    fn deadline(&self, timeout: Duration) -> Option<Self::Instant>;
+
+    /// 受信したバイト列を `framer` に追加します。
+    /// 期限を過ぎても何も受信できなければ [`Error::Timeout`] を返します。
+    async fn fill(&mut self, framer: &mut Framer, deadline: Option<Self::Instant>) -> Result<()>;
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()>;
+
+    async fn flush(&mut self) -> Result<()>;
+}
+
+/// 決して中断しない（同期的に読み書きする）通信路を使った処理を、完了するまで実行します。
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("blocking I/O never suspends"),
+    }
+}
+
+pub(crate) struct Protocol<I> {
+    pub(crate) io: I,
+    pub(crate) buf: VecDeque<Payload>,
+
+    /// 読み込み途中のフレーム（タイムアウトで中断された場合に次回の読み込みへ引き継ぐ）
+    pub(crate) framer: Framer,
+
+    pub(crate) echo: EchoBack,
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn connect(io: I) -> Result<Self> {
+        let mut this = Self {
+            io,
+            buf: Default::default(),
+            framer: Framer::default(),
+            echo: EchoBack::default(),
+        };
+
+        // バッファに溜まっているコマンドと被って SKRESET がエラーにならないように CRLF を送信
+        // エコーバックが有効であれば空行が返ってくるので、空のコマンドとして記録しておく
+        this.echo.sent(&[]);
+        this.send_crlf().await?;
+
+        // リセット
+        this.reset().await?;
+
+        // エコーバックは要らないので切っておく
+        this.set_register(sksreg::Register::SFE, sksreg::Value::Bool(false))
+            .await?;
+
+        Ok(this)
+    }
+
+    /// 1 件の応答（通常は 1 行、バイナリの末尾を持つ応答はその末尾まで）を読み込みます。
+    pub(crate) async fn receive_frame_until(
+        &mut self,
+        deadline: Option<I::Instant>,
+    ) -> Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = self.framer.next_frame() {
+                if self.echo.strip(&frame) {
+                    continue; // skipping echo-backs
+                }
+
+                return Ok(frame);
+            }
+
+            self.io.fill(&mut self.framer, deadline).await?;
+        }
+    }
+
+    /// 受信済みでまだ処理されていないデータをすべて破棄します。
+    #[cfg(feature = "std")]
+    pub(crate) fn discard_input(&mut self) {
+        self.buf.clear();
+        self.framer.clear();
+        self.echo.clear();
+    }
+
+    pub(crate) async fn receive_payload_until(
+        &mut self,
+        deadline: Option<I::Instant>,
+    ) -> Result<Payload> {
+        match self.buf.pop_front() {
+            Some(payload) => Ok(payload),
+            _ => self.receive_payload_unbuffered_until(deadline).await,
+        }
+    }
+
+    pub(crate) async fn receive_payload_unbuffered_until(
+        &mut self,
+        deadline: Option<I::Instant>,
+    ) -> Result<Payload> {
+        let payload = Payload::from(self.receive_frame_until(deadline).await?);
+
+        debug!("< {payload:?}");
+
+        Ok(payload)
+    }
+
+    async fn send_crlf(&mut self) -> Result<()> {
+        self.io.write(CRLF).await?;
+        self.io.flush().await
+    }
+
+    pub(crate) async fn send_payload(&mut self, payload: &Payload) -> Result<()> {
+        debug!("> {payload:?}");
+
+        let bytes = Vec::<u8>::from(payload);
+        self.echo.sent(&bytes);
+
+        self.io.write(bytes.as_slice()).await?;
+        self.send_crlf().await
+    }
+
+    pub(crate) async fn send<E>(&mut self, input: &E) -> Result<()>
+    where
+        E: Encode,
+    {
+        self.send_payload(&input.encode()).await
+    }
+
+    /// 受信したペイロードを `f` に渡し、[`WaitMap::Finish`] が返されるまで待機します。
+    /// タイムアウトやエラーで中断された場合も、それまでに受信したペイロードは後続の待機のためにバッファに残します。
+    pub(crate) async fn wait_map_until<F, T>(
+        &mut self,
+        deadline: Option<I::Instant>,
+        mut f: F,
+    ) -> Result<T>
+    where
+        F: FnMut(Payload) -> WaitMap<T>,
+    {
+        let mut buf = Vec::<Payload>::new();
+
+        let result = loop {
+            let payload = match self.receive_payload_until(deadline).await {
+                Ok(p) => p,
+                Err(e) => break Err(e),
+            };
+
+            match f(payload) {
+                WaitMap::Consume => {}
+                WaitMap::Continue(payload) => {
+                    buf.push(payload);
+                }
+                WaitMap::Finish(value) => {
+                    break Ok(value);
+                }
+            }
+        };
+
+        buf.into_iter().for_each(|p| self.buf.push_back(p));
+
+        result
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) async fn wait_for_until<F>(
+        &mut self,
+        deadline: Option<I::Instant>,
+        criteria: F,
+    ) -> Result<Payload>
+    where
+        F: Fn(&Payload) -> bool,
+    {
+        self.wait_map_until(deadline, |p| {
+            if criteria(&p) {
+                WaitMap::Finish(p)
+            } else {
+                WaitMap::Continue(p)
+            }
+        })
+        .await
+    }
+
+    pub(crate) async fn wait_for_event_until<F>(
+        &mut self,
+        deadline: Option<I::Instant>,
+        criteria: F,
+    ) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        self.wait_map_until(deadline, |p| {
+            if p.name == EVENT {
+                match Event::try_from(&p) {
+                    Ok(event) if criteria(&event) => return WaitMap::Finish(event),
+                    Err(e) => {
+                        warn!("Ignoring malformed EVENT: {e}");
+                        return WaitMap::Consume;
+                    }
+                    _ => {}
+                }
+            }
+
+            WaitMap::Continue(p)
+        })
+        .await
+    }
+
+    pub(crate) async fn wait_for_ok_until(&mut self, deadline: Option<I::Instant>) -> Result<()> {
+        self.wait_for_name(deadline, OK).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn wait_for_response_until<R>(
+        &mut self,
+        deadline: Option<I::Instant>,
+    ) -> Result<R>
+    where
+        R: Response,
+    {
+        let payload = self.wait_for_name(deadline, R::NAME).await?;
+        let response = R::decode(&payload);
+
+        // デコードに失敗しても、後続のコマンドが OK を取り違えないように読み捨てる
+        self.wait_for_ok_until(deadline).await?;
+
+        response
+    }
+
+    /// 指定した名前の応答か `FAIL ERxx` を受信するまで待機します。
+    async fn wait_for_name(
+        &mut self,
+        deadline: Option<I::Instant>,
+        name: &[u8],
+    ) -> Result<Payload> {
+        self.wait_map_until(deadline, |p| {
+            if p.name == name {
+                WaitMap::Finish(Ok(p))
+            } else if p.name == FAIL {
+                WaitMap::Finish(Err(Error::Fail(FailCode::from(&p))))
+            } else {
+                WaitMap::Continue(p)
+            }
+        })
+        .await?
+    }
+}
+
+/// 各ドライバで公開するコマンドの一覧
+///
+/// 引数に渡したマクロへ一覧を展開するので、ドライバはそれぞれの待ち方で [`Protocol`] を呼び出す公開メソッドを生成できます。
+/// ドライバ間で使える API が食い違わないように、コマンドを追加する場合はここに加えてください。
+///
+/// PAN への参加や UDP の送受信といった PANA セッションを前提とするコマンドは `pub(crate)` にしており、
+/// どのドライバでも [`crate::session::Session`] を通してのみ使えます。
+#[cfg(any(feature = "std", feature = "embedded-io"))]
+macro_rules! commands {
+    ($driver:ident) => {
+        $driver! {
+            /// ファームウェアのバージョンを返します。
+            pub fn version() -> crate::cmd::skver::Output;
+
+            /// 自端末の IPv6 アドレスや MAC アドレス、チャネルなどを返します。
+            pub fn info() -> crate::cmd::skinfo::Output;
+
+            /// PANA 認証に使うパスワードを設定します。
+            pub fn set_pwd(pwd: &[u8]) -> ();
+
+            /// PANA 認証に使うルート B ID を設定します。
+            pub fn set_rbid(rbid: [u8; 16]) -> ();
+
+            /// MAC アドレスから IPv6 リンクローカルアドレスを求めます。
+            pub fn mac_to_ip_addr(addr_64: [u8; 8]) -> crate::cmd::skll64::Output;
+
+            /// アクティブスキャンを行い、見つかった PAN を返します。
+            /// `duration` が [`crate::cmd::skscan::MAX_DURATION`] を超える場合は、送信せずに [`crate::Error::OutOfRange`] を返します。
+            pub fn scan_active(
+                ie: bool,
+                channel_mask: u32,
+                duration: u8,
+                side: u8
+            ) -> alloc::vec::Vec<crate::event::epandesc::EPanDesc>;
+
+            /// 指定したチャネルのエネルギーを計測し、チャネルごとの RSSI を返します。
+            /// `duration` が [`crate::cmd::skscan::MAX_DURATION`] を超える場合は、送信せずに [`crate::Error::OutOfRange`] を返します。
+            pub fn scan_energy(
+                channel_mask: u32,
+                duration: u8,
+                side: u8
+            ) -> alloc::vec::Vec<crate::event::eedscan::EnergyLevel>;
+
+            /// 指定した相手に PANA 認証を行い、結果を返します。
+            pub(crate) fn join(ip_addr: core::net::Ipv6Addr) -> crate::cmd::skjoin::Output;
+
+            /// 現在接続中の相手に対して PANA の再認証を行います。
+            pub(crate) fn rejoin() -> crate::cmd::skjoin::Output;
+
+            /// PANA セッションを終了します。
+            pub(crate) fn terminate() -> crate::cmd::skterm::Output;
+
+            /// UDP パケットを送信し、送信結果 (EVENT 21) を返します。
+            /// 引数が [`crate::cmd::sksendto::Input::validate`] の範囲を外れる場合は、送信せずに [`crate::Error::OutOfRange`] を返します。
+            pub(crate) fn send_to(
+                handle: u8,
+                ip_addr: core::net::Ipv6Addr,
+                port: u16,
+                security: crate::cmd::sksendto::Security,
+                side: u8,
+                data: &[u8]
+            ) -> crate::event::UDPSendResult;
+
+            /// UDP パケットを受信するまで待機します。
+            pub(crate) fn receive_udp() -> crate::event::erxudp::ERxUdp;
+
+            /// 現在のレジスタの設定をフラッシュメモリに保存します。
+            /// モジュールが `FAIL ERxx` を返した場合は [`crate::Error::Flash`] を返します。
+            pub fn save_config() -> ();
+
+            /// フラッシュメモリに保存された設定を読み込みます。
+            /// モジュールが `FAIL ERxx` を返した場合は [`crate::Error::Flash`] を返します。
+            pub fn load_config() -> ();
+
+            /// フラッシュメモリに保存された設定を消去します。
+            /// モジュールが `FAIL ERxx` を返した場合は [`crate::Error::Flash`] を返します。
+            pub fn erase_config() -> ();
+
+            /// レジスタの値を、レジスタの型に従って読み出します。
+            pub fn read_register(register: crate::cmd::sksreg::Register) -> crate::cmd::sksreg::Output;
+
+            /// レジスタに値を書き込みます。
+            /// 値がレジスタの型や値域に合わない場合は、送信せずに [`crate::Error::OutOfRange`] を返します。
+            pub fn set_register(
+                register: crate::cmd::sksreg::Register,
+                value: crate::cmd::sksreg::Value
+            ) -> ();
+
+            /// 自端末が使用するチャネル (S02)
+            pub fn channel() -> crate::cmd::sksreg::Channel;
+
+            pub fn set_channel(channel: crate::cmd::sksreg::Channel) -> ();
+
+            /// 自端末の PAN ID (S03)
+            pub fn pan_id() -> u16;
+
+            pub fn set_pan_id(pan_id: u16) -> ();
+
+            /// MAC 層のフレームカウンタ (S07)
+            pub fn frame_counter() -> u32;
+
+            /// ペアリング ID (S0A)
+            pub fn pairing_id() -> [u8; 4];
+
+            pub fn set_pairing_id(pairing_id: [u8; 4]) -> ();
+
+            /// ビーコン要求に応答するかどうか (S15)
+            pub fn beacon_response() -> bool;
+
+            pub fn set_beacon_response(enabled: bool) -> ();
+
+            /// PANA セッションライフタイム (S16)
+            pub fn pana_session_lifetime() -> core::time::Duration;
+
+            /// PANA セッションライフタイムを秒単位で設定します。
+            /// [`crate::cmd::sksreg::MIN_PANA_SESSION_LIFETIME`] 未満や 32 ビットに収まらない時間を指定すると
+            /// [`crate::Error::OutOfRange`] を返します。
+            pub fn set_pana_session_lifetime(lifetime: core::time::Duration) -> ();
+
+            /// PANA セッションを自動で再認証するかどうか (S17)
+            pub fn auto_reauth() -> bool;
+
+            pub fn set_auto_reauth(enabled: bool) -> ();
+
+            /// ICMP メッセージ処理制御フラグ (SA1)
+            pub fn icmp_handling() -> bool;
+
+            pub fn set_icmp_handling(enabled: bool) -> ();
+
+            /// アクティブな MAC 面 (SF0)
+            /// B 面であれば 0、H 面であれば 1 を返します。
+            pub fn active_side() -> u8;
+
+            /// アクティブな MAC 面を設定します。0, 1 以外を指定すると [`crate::Error::OutOfRange`] を返します。
+            pub fn set_active_side(side: u8) -> ();
+
+            /// 電波法による送信時間の制限に達していて、送信できない状態かどうか (SFB)
+            pub fn transmit_limited() -> bool;
+
+            /// 無線送信の積算時間 (SFD)
+            /// 単位を確認できていないため、レジスタの値をそのまま返します。
+            pub fn transmit_time() -> u32;
+
+            /// 起動時に SKSAVE で保存された設定を読み込むかどうか (SFF)
+            pub fn auto_load() -> bool;
+
+            pub fn set_auto_load(enabled: bool) -> ();
+
+            /// すべてのレジスタの値を読み出します。
+            pub fn snapshot_registers() -> crate::snapshot::Snapshot;
+
+            /// 現在の値が `snapshot` と異なる書き込み可能なレジスタだけを書き戻し、書き戻した変更を返します。
+            pub fn restore_registers(
+                snapshot: &crate::snapshot::Snapshot
+            ) -> alloc::vec::Vec<crate::snapshot::Change>;
+        }
+    };
+}
+
+#[cfg(any(feature = "std", feature = "embedded-io"))]
+pub(crate) use commands;
@@ -0,0 +1,170 @@
+//! プロトコルを直接扱う低レベルな API
+//!
+//! コマンドの送信と応答の待機を個別に行えますが、応答を読み捨てたりエコーバックなどのレジスタを書き換えたりすると、
+//! [`Bp35c0`] の高レベルな API が前提としている状態と食い違うことがあります。
+
+use std::net::Ipv6Addr;
+use std::time::Instant;
+
+use crate::{Bp35c0, Result, Transport, WaitMap};
+use crate::cmd::{sksreg, Encode, Response};
+use crate::event::eedscan::EnergyLevel;
+use crate::event::epandesc::EPanDesc;
+use crate::protocol::block_on;
+
+pub use crate::payload::Payload;
+
+/// [`Bp35c0::raw`] で得られる低レベルなハンドル
+pub struct Raw<'a, Port: Transport> {
+    device: &'a mut Bp35c0<Port>,
+}
+
+impl<Port: Transport> Bp35c0<Port> {
+    /// プロトコルを直接扱う低レベルな API を返します。
+    pub fn raw(&mut self) -> Raw<'_, Port> {
+        Raw { device: self }
+    }
+}
+
+impl<Port: Transport> Raw<'_, Port> {
+    pub fn receive_payload(&mut self) -> Result<Payload> {
+        self.receive_payload_until(None)
+    }
+
+    pub fn receive_payload_until(&mut self, deadline: Option<Instant>) -> Result<Payload> {
+        block_on(self.device.core.receive_payload_until(deadline))
+    }
+
+    pub fn receive_payload_unbuffered(&mut self) -> Result<Payload> {
+        self.receive_payload_unbuffered_until(None)
+    }
+
+    pub fn receive_payload_unbuffered_until(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Payload> {
+        block_on(self.device.core.receive_payload_unbuffered_until(deadline))
+    }
+
+    pub fn send_payload(&mut self, payload: &Payload) -> Result<()> {
+        block_on(self.device.core.send_payload(payload))
+    }
+
+    pub fn send<E>(&mut self, input: &E) -> Result<()>
+    where
+        E: Encode,
+    {
+        block_on(self.device.core.send(input))
+    }
+
+    pub fn wait_map<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnMut(Payload) -> WaitMap<T>,
+    {
+        self.wait_map_until(None, f)
+    }
+
+    pub fn wait_map_until<F, T>(&mut self, deadline: Option<Instant>, f: F) -> Result<T>
+    where
+        F: FnMut(Payload) -> WaitMap<T>,
+    {
+        block_on(self.device.core.wait_map_until(deadline, f))
+    }
+
+    pub fn wait_for<F>(&mut self, criteria: F) -> Result<Payload>
+    where
+        F: Fn(&Payload) -> bool,
+    {
+        self.wait_for_until(None, criteria)
+    }
+
+    pub fn wait_for_until<F>(&mut self, deadline: Option<Instant>, criteria: F) -> Result<Payload>
+    where
+        F: Fn(&Payload) -> bool,
+    {
+        block_on(self.device.core.wait_for_until(deadline, criteria))
+    }
+
+    /// `OK` を受信するまで待機します。
+    /// 先に `FAIL ERxx` を受信した場合は [`crate::Error::Fail`] を返します。
+    pub fn wait_for_ok(&mut self) -> Result<()> {
+        self.wait_for_ok_until(None)
+    }
+
+    pub fn wait_for_ok_until(&mut self, deadline: Option<Instant>) -> Result<()> {
+        block_on(self.device.core.wait_for_ok_until(deadline))
+    }
+
+    pub fn wait_for_response<R>(&mut self) -> Result<R>
+    where
+        R: Response,
+    {
+        self.wait_for_response_until(None)
+    }
+
+    pub fn wait_for_response_until<R>(&mut self, deadline: Option<Instant>) -> Result<R>
+    where
+        R: Response,
+    {
+        block_on(self.device.core.wait_for_response_until(deadline))
+    }
+
+    pub fn reset(&mut self) -> Result<()> {
+        block_on(self.device.core.reset())
+    }
+
+    pub fn read_register(&mut self, register: sksreg::Register) -> Result<sksreg::Output> {
+        block_on(self.device.core.read_register(register))
+    }
+
+    pub fn set_register(&mut self, register: sksreg::Register, value: sksreg::Value) -> Result<()> {
+        block_on(self.device.core.set_register(register, value))
+    }
+
+    pub fn join_nowait(&mut self, ip_addr: Ipv6Addr) -> Result<()> {
+        block_on(self.device.core.join_nowait(ip_addr))
+    }
+
+    pub fn scan_active_nowait(
+        &mut self,
+        ie: bool,
+        channel_mask: u32,
+        duration: u8,
+        side: u8,
+    ) -> Result<()> {
+        block_on(
+            self.device
+                .core
+                .scan_active_nowait(ie, channel_mask, duration, side),
+        )
+    }
+
+    pub fn scan_energy_nowait(&mut self, channel_mask: u32, duration: u8, side: u8) -> Result<()> {
+        block_on(
+            self.device
+                .core
+                .scan_energy_nowait(channel_mask, duration, side),
+        )
+    }
+
+    /// EEDSCAN に続いて出力される `<CHANNEL> <RSSI> ...` の行を読み込みます。
+    pub fn receive_eedscan(&mut self, payload: &Payload) -> Result<Vec<EnergyLevel>> {
+        self.receive_eedscan_until(None, payload)
+    }
+
+    pub fn receive_eedscan_until(
+        &mut self,
+        deadline: Option<Instant>,
+        payload: &Payload,
+    ) -> Result<Vec<EnergyLevel>> {
+        block_on(self.device.core.receive_eedscan_until(deadline, payload))
+    }
+
+    pub fn receive_epandesc(&mut self) -> Result<EPanDesc> {
+        self.receive_epandesc_until(None)
+    }
+
+    pub fn receive_epandesc_until(&mut self, deadline: Option<Instant>) -> Result<EPanDesc> {
+        block_on(self.device.core.receive_epandesc_until(deadline))
+    }
+}
@@ -0,0 +1,373 @@
+//! 状態に応じて使える操作が決まる高レベルな API
+//!
+//! [`Session`] は接続 → 認証情報の設定 → PAN への参加の順に状態が遷移し、
+//! 例えば UDP の送信は PAN に参加した状態 ([`Joined`]) でのみ行えます。
+//! 状態の遷移に失敗した場合は、元の状態のセッションが [`Failure`] に入って返されるので、そのままやり直せます。
+//!
+//! [`Session`] はドライバを型引数に取り、同期版は [`crate::Session`]、非同期版は `tokio::Session`、
+//! `no_std` 版は `embedded::Session` として使います。どのドライバでも、PAN への参加や UDP の送受信はセッションを通してのみ行えます。
+
+use alloc::boxed::Box;
+use core::fmt::{Debug, Display, Formatter};
+use core::net::Ipv6Addr;
+
+use crate::Error;
+use crate::event::epandesc::EPanDesc;
+
+/// UDP の送信に使うハンドル
+pub(crate) const UDP_HANDLE: u8 = 1;
+
+/// モジュールと接続した状態
+pub struct Connected;
+
+/// Route-B の認証 ID とパスワードを設定した状態
+pub struct Authenticated;
+
+/// PAN に参加した状態
+pub struct Joined {
+    coordinator: Ipv6Addr,
+    pan: EPanDesc,
+}
+
+impl Joined {
+    pub(crate) fn new(coordinator: Ipv6Addr, pan: EPanDesc) -> Self {
+        Self { coordinator, pan }
+    }
+}
+
+/// 状態の遷移に失敗したときに、元の状態のセッションとエラーを保持します。
+pub struct Failure<S> {
+    pub session: S,
+    pub error: Error,
+}
+
+impl<S> Debug for Failure<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Failure")
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> Display for Failure<S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl<S> core::error::Error for Failure<S> {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl<S> From<Box<Failure<S>>> for Error {
+    fn from(value: Box<Failure<S>>) -> Self {
+        value.error
+    }
+}
+
+/// 状態の遷移の結果
+///
+/// セッションを保持していて大きいため、失敗はボックス化して返します。
+pub type Transition<T, S> = core::result::Result<T, Box<Failure<S>>>;
+
+/// 状態 `State` にあるドライバ `D` のセッション
+pub struct Session<State, D> {
+    device: D,
+    state: State,
+}
+
+impl<State, D> Session<State, D> {
+    pub(crate) fn into_state<T>(self, state: T) -> Session<T, D> {
+        Session {
+            device: self.device,
+            state,
+        }
+    }
+
+    pub(crate) fn fail(self, error: Error) -> Box<Failure<Self>> {
+        Box::new(Failure {
+            session: self,
+            error,
+        })
+    }
+
+    pub(crate) fn device(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    /// セッションを終了し、ドライバを取り出します。
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+}
+
+impl<D> From<D> for Session<Connected, D> {
+    fn from(device: D) -> Self {
+        Self {
+            device,
+            state: Connected,
+        }
+    }
+}
+
+impl<D> Session<Joined, D> {
+    /// 参加している PAN のコーディネータのアドレス
+    pub fn coordinator(&self) -> Ipv6Addr {
+        self.state.coordinator
+    }
+
+    /// 参加している PAN
+    pub fn pan(&self) -> &EPanDesc {
+        &self.state.pan
+    }
+
+    /// PANA セッションが既に失われている場合に、認証情報を設定した状態に戻します。
+    #[cfg(feature = "std")]
+    pub(crate) fn into_authenticated(self) -> Session<Authenticated, D> {
+        self.into_state(Authenticated)
+    }
+}
+
+/// ドライバ `$driver` の [`Session`] に、状態ごとの操作をそのドライバの待ち方で実装します。
+///
+/// 応答を受信するまでブロックするドライバは `blocking`、非同期に待つドライバは `nonblocking` を指定します。
+/// 操作の中身はドライバ間で共通なので、状態による制限がドライバごとに食い違うことはありません。
+macro_rules! session {
+    (blocking, impl<$($param:ident),*> $driver:ty where $($bounds:tt)*) => {
+        $crate::session::session!(@impl [] [], [$($param),*], $driver, [$($bounds)*]);
+    };
+    (nonblocking, impl<$($param:ident),*> $driver:ty where $($bounds:tt)*) => {
+        $crate::session::session!(@impl [async] [.await], [$($param),*], $driver, [$($bounds)*]);
+    };
+    (
+        @impl [$($async:tt)*] [$($await:tt)*],
+        [$($param:ident),*],
+        $driver:ty,
+        [$($bounds:tt)*]
+    ) => {
+        impl<State, $($param),*> $crate::session::Session<State, $driver>
+        where
+            $($bounds)*
+        {
+            pub $($async)* fn version(&mut self) -> $crate::Result<$crate::cmd::skver::Output> {
+                self.device().version() $($await)*
+            }
+
+            pub $($async)* fn info(&mut self) -> $crate::Result<$crate::cmd::skinfo::Output> {
+                self.device().info() $($await)*
+            }
+
+            pub $($async)* fn scan_active(
+                &mut self,
+                ie: bool,
+                channel_mask: u32,
+                duration: u8,
+                side: u8,
+            ) -> $crate::Result<alloc::vec::Vec<$crate::event::epandesc::EPanDesc>> {
+                self.device().scan_active(ie, channel_mask, duration, side) $($await)*
+            }
+
+            /// 指定したチャネルのエネルギーを計測し、チャネルごとの RSSI を返します。
+            pub $($async)* fn scan_energy(
+                &mut self,
+                channel_mask: u32,
+                duration: u8,
+                side: u8,
+            ) -> $crate::Result<alloc::vec::Vec<$crate::event::eedscan::EnergyLevel>> {
+                self.device().scan_energy(channel_mask, duration, side) $($await)*
+            }
+
+            pub $($async)* fn wait_for_event<F>(&mut self, criteria: F) -> $crate::Result<$crate::event::Event>
+            where
+                F: Fn(&$crate::event::Event) -> bool,
+            {
+                self.device().wait_for_event(criteria) $($await)*
+            }
+        }
+
+        impl<$($param),*> $crate::session::Session<$crate::session::Connected, $driver>
+        where
+            $($bounds)*
+        {
+            /// Route-B の認証 ID とパスワードを設定します。
+            pub $($async)* fn authenticate(
+                mut self,
+                rbid: [u8; 16],
+                pwd: &[u8],
+            ) -> $crate::session::Transition<
+                $crate::session::Session<$crate::session::Authenticated, $driver>,
+                Self,
+            > {
+                match self.try_authenticate(rbid, pwd) $($await)* {
+                    Ok(()) => Ok(self.into_state($crate::session::Authenticated)),
+                    Err(e) => Err(self.fail(e)),
+                }
+            }
+
+            $($async)* fn try_authenticate(&mut self, rbid: [u8; 16], pwd: &[u8]) -> $crate::Result<()> {
+                self.device().set_rbid(rbid) $($await)* ?;
+                self.device().set_pwd(pwd) $($await)*
+            }
+        }
+
+        impl<$($param),*> $crate::session::Session<$crate::session::Authenticated, $driver>
+        where
+            $($bounds)*
+        {
+            /// スキャンで見つけた PAN に参加します。
+            pub $($async)* fn join(
+                mut self,
+                pan: &$crate::event::epandesc::EPanDesc,
+            ) -> $crate::session::Transition<
+                $crate::session::Session<$crate::session::Joined, $driver>,
+                Self,
+            > {
+                match self.try_join(pan) $($await)* {
+                    Ok(coordinator) => {
+                        Ok(self.into_state($crate::session::Joined::new(coordinator, pan.clone())))
+                    }
+                    Err(e) => Err(self.fail(e)),
+                }
+            }
+
+            $($async)* fn try_join(
+                &mut self,
+                pan: &$crate::event::epandesc::EPanDesc,
+            ) -> $crate::Result<core::net::Ipv6Addr> {
+                let device = self.device();
+                device
+                    .set_channel($crate::cmd::sksreg::Channel::new(pan.channel)?)
+                    $($await)* ?;
+                device.set_pan_id(pan.pan_id) $($await)* ?;
+
+                let coordinator = device.mac_to_ip_addr(pan.addr) $($await)* ?.ip_addr;
+
+                match device.join(coordinator) $($await)* ? {
+                    $crate::cmd::skjoin::Output::Connected => Ok(coordinator),
+                    $crate::cmd::skjoin::Output::Failed => Err($crate::Error::JoinFailed),
+                }
+            }
+        }
+
+        impl<$($param),*> $crate::session::Session<$crate::session::Joined, $driver>
+        where
+            $($bounds)*
+        {
+            /// コーディネータに UDP パケットを暗号化して送信します。
+            pub $($async)* fn send_udp(
+                &mut self,
+                port: u16,
+                data: &[u8],
+            ) -> $crate::Result<$crate::event::UDPSendResult> {
+                self.send_to(self.coordinator(), port, data) $($await)*
+            }
+
+            /// 指定したアドレスに UDP パケットを暗号化して送信します。
+            pub $($async)* fn send_to(
+                &mut self,
+                ip_addr: core::net::Ipv6Addr,
+                port: u16,
+                data: &[u8],
+            ) -> $crate::Result<$crate::event::UDPSendResult> {
+                let side = self.pan().side;
+                self.device()
+                    .send_to(
+                        $crate::session::UDP_HANDLE,
+                        ip_addr,
+                        port,
+                        $crate::cmd::sksendto::Security::Encrypted,
+                        side,
+                        data,
+                    )
+                    $($await)*
+            }
+
+            pub $($async)* fn receive_udp(&mut self) -> $crate::Result<$crate::event::erxudp::ERxUdp> {
+                self.device().receive_udp() $($await)*
+            }
+
+            pub $($async)* fn wait_for_udp<F>(
+                &mut self,
+                criteria: F,
+            ) -> $crate::Result<$crate::event::erxudp::ERxUdp>
+            where
+                F: Fn(&$crate::event::erxudp::ERxUdp) -> bool,
+            {
+                self.device().wait_for_udp(criteria) $($await)*
+            }
+
+            /// PANA の再認証を行います。
+            pub $($async)* fn rejoin(&mut self) -> $crate::Result<()> {
+                match self.device().rejoin() $($await)* ? {
+                    $crate::cmd::skjoin::Output::Connected => Ok(()),
+                    $crate::cmd::skjoin::Output::Failed => Err($crate::Error::JoinFailed),
+                }
+            }
+
+            /// PANA セッションを終了し、PAN から離脱します。
+            ///
+            /// 終了要求への応答が無かった (EVENT 28) 場合も、モジュールはセッションを終了しているので認証情報を設定した状態に戻ります。
+            /// どちらで終了したかは、一緒に返す [`crate::cmd::skterm::Output`] で確認できます。
+            pub $($async)* fn terminate(
+                mut self,
+            ) -> $crate::session::Transition<
+                (
+                    $crate::session::Session<$crate::session::Authenticated, $driver>,
+                    $crate::cmd::skterm::Output,
+                ),
+                Self,
+            > {
+                match self.device().terminate() $($await)* {
+                    Ok(output) => Ok((self.into_state($crate::session::Authenticated), output)),
+                    Err(e) => Err(self.fail(e)),
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use session;
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::{Error, Session};
+    #[cfg(feature = "std")]
+    use crate::cmd::skterm;
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_session() {
+        let mock = MockBp35c0::with_test_pan();
+
+        let mut session = Session::connect(mock.clone()).unwrap();
+        let pan = session
+            .scan_active(true, 0xFFFFFFFF, 6, 0)
+            .unwrap()
+            .remove(0);
+
+        let session = session.authenticate([0x30; 16], b"PASSWORD").unwrap();
+
+        mock.push_join_result(false);
+        let failure = session.join(&pan).err().unwrap();
+        assert!(matches!(failure.error, Error::JoinFailed));
+
+        let session = failure.session.join(&pan).unwrap();
+        assert_eq!("fe80::21d:1290:0:2".parse(), Ok(session.coordinator()));
+        assert_eq!(Some(b"3B".to_vec()), mock.register(b"S02"));
+        assert_eq!(Some(b"1234".to_vec()), mock.register(b"S03"));
+
+        // 終了要求に応答が無くても、認証情報を設定した状態に戻る
+        mock.push_terminate_result(false);
+        let (session, output) = session.terminate().unwrap();
+        assert_eq!(skterm::Output::TimedOut, output);
+
+        let session = session.join(&pan).unwrap();
+        let (_, output) = session.terminate().unwrap();
+        assert_eq!(skterm::Output::Terminated, output);
+    }
+}
@@ -0,0 +1,147 @@
+//! レジスタの値の一括取得と比較、書き戻し
+//!
+//! 正常に動作しているモジュールの [`Snapshot`] を取っておけば、不調なモジュールの設定との違いを [`Snapshot::diff`] で調べたり、
+//! `restore_registers` で同じ設定に戻したりできます。
+//! `serde` フィーチャを有効にすると [`Snapshot`] をシリアライズできます。
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::{Error, Result};
+use crate::cmd::sksreg::{Kind, Register, Value};
+use crate::protocol::{Io, Protocol};
+
+/// ある時点でのすべてのレジスタの値
+///
+/// 読み出しに失敗した（ファームウェアが対応していない）レジスタは含みません。
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub registers: BTreeMap<Register, Value>,
+}
+
+/// 2 つのスナップショットで値が異なるレジスタ
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Change {
+    pub register: Register,
+
+    /// 比較元の値（含まれていなければ `None`）
+    pub before: Option<Value>,
+
+    /// 比較先の値（含まれていなければ `None`）
+    pub after: Option<Value>,
+}
+
+impl Snapshot {
+    pub fn get(&self, register: Register) -> Option<&Value> {
+        self.registers.get(&register)
+    }
+
+    /// `other` と値が異なるレジスタを、レジスタの番号順に返します。
+    pub fn diff(&self, other: &Snapshot) -> Vec<Change> {
+        Register::ALL
+            .iter()
+            .map(|r| Change {
+                register: *r,
+                before: self.get(*r).cloned(),
+                after: other.get(*r).cloned(),
+            })
+            .filter(|c| c.before != c.after)
+            .collect()
+    }
+}
+
+/// 書き戻しの対象にするレジスタかどうかを判定します。
+/// 読み出し専用のレジスタと、型を確認できていないレジスタは書き戻しません。
+fn is_restorable(register: Register) -> bool {
+    register.is_writable() && register.kind() != Kind::Unknown
+}
+
+impl<I: Io> Protocol<I> {
+    pub(crate) async fn snapshot_registers(&mut self) -> Result<Snapshot> {
+        let mut snapshot = Snapshot::default();
+
+        for register in Register::ALL {
+            match self.read_register(*register).await {
+                Ok(output) => {
+                    snapshot.registers.insert(*register, output.value);
+                }
+                Err(Error::Fail(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(snapshot)
+    }
+
+    pub(crate) async fn restore_registers(&mut self, snapshot: &Snapshot) -> Result<Vec<Change>> {
+        let changes = self
+            .snapshot_registers()
+            .await?
+            .diff(snapshot)
+            .into_iter()
+            .filter(|c| is_restorable(c.register) && c.after.is_some())
+            .collect::<Vec<_>>();
+
+        for change in &changes {
+            if let Some(value) = &change.after {
+                self.set_register(change.register, value.clone()).await?;
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::Bp35c0;
+    use crate::cmd::sksreg::{Register, Value};
+    #[cfg(feature = "std")]
+    use crate::mock::MockBp35c0;
+    use crate::snapshot::Snapshot;
+
+    #[test]
+    fn test_diff() {
+        let mut before = Snapshot::default();
+        before.registers.insert(Register::S02, Value::Uint8(0x21));
+        before.registers.insert(Register::S17, Value::Bool(true));
+
+        let mut after = before.clone();
+        after.registers.insert(Register::S02, Value::Uint8(0x3B));
+        after.registers.insert(Register::S07, Value::Uint32(1));
+
+        let changes = before.diff(&after);
+        assert_eq!(2, changes.len());
+        assert_eq!(Register::S02, changes[0].register);
+        assert_eq!(Some(Value::Uint8(0x3B)), changes[0].after);
+        assert_eq!(Register::S07, changes[1].register);
+        assert_eq!(None, changes[1].before);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_restore() {
+        let mock = MockBp35c0::new();
+        let mut device = Bp35c0::connect(mock.clone()).unwrap();
+
+        let snapshot = device.snapshot_registers().unwrap();
+        assert_eq!(Some(&Value::Uint8(0x21)), snapshot.get(Register::S02));
+        assert_eq!(Some(&Value::Bool(false)), snapshot.get(Register::SFE));
+
+        let mut target = snapshot.clone();
+        target.registers.insert(Register::S02, Value::Uint8(0x3B));
+        target.registers.insert(Register::S07, Value::Uint32(1));
+
+        // 読み出し専用の S07 は書き戻さない
+        let changes = device.restore_registers(&target).unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!(Register::S02, changes[0].register);
+        assert_eq!(Some(b"3B".to_vec()), mock.register(b"S02"));
+        assert_eq!(Some(b"00000000".to_vec()), mock.register(b"S07"));
+
+        assert!(device.restore_registers(&target).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,524 @@
+//! PANA セッションを監視し、失われたときに自動で再接続するスーパーバイザ
+//!
+//! PANA セッションの喪失を示すイベント (EVENT 24, 27, 28, 29) を受信すると、まず SKREJOIN で再認証を試み、
+//! それに失敗した場合はアクティブスキャンからやり直して PAN に参加し直します。
+//! スキャンからのやり直しは、指数関数的に間隔を空けながら試行回数の上限まで繰り返します。
+//! 上限に達して諦めた場合も、次に UDP を送受信する際に再接続をやり直します。
+//!
+//! コーディネータから切断を要求された場合 (EVENT 26) は、続いて通知される EVENT 27 で再接続します。
+
+use std::net::Ipv6Addr;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime};
+
+use serialport::SerialPort;
+use tracing::{info, warn};
+
+use crate::{Error, FailCode, Result, Session, Transport, WaitMap};
+use crate::event::erxudp::{ERxUdp, ERXUDP};
+use crate::event::{Event, EVENT, EventBody, UDPSendResult};
+use crate::event::epandesc::EPanDesc;
+use crate::session::{Authenticated, Joined};
+
+/// PANA セッションの状態
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PanaState {
+    /// 接続している
+    Connected,
+
+    /// セッションが失われ、再接続を諦めた
+    Disconnected,
+}
+
+/// 再接続の方法
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Method {
+    /// SKREJOIN による再認証
+    Rejoin,
+
+    /// アクティブスキャンからのやり直し
+    Rescan,
+}
+
+/// 再接続に失敗した理由
+///
+/// [`Error`] は [`Clone`] できないため、記録に必要な情報だけを保持します。
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReconnectError {
+    /// ポートの読み書きに失敗した
+    Io(std::io::ErrorKind),
+
+    /// 応答を待っている間にタイムアウトした
+    Timeout,
+
+    /// モジュールからの応答を解釈できなかった
+    Parse { field: &'static str },
+
+    /// モジュールが `FAIL ERxx` を返した
+    Fail(FailCode),
+
+    /// PANA 認証に失敗した (EVENT 24)
+    JoinFailed,
+
+    /// スキャンで元の PAN が見つからなかった
+    PanNotFound,
+
+    /// 上記以外のエラー
+    Other(String),
+}
+
+impl From<&Error> for ReconnectError {
+    fn from(value: &Error) -> Self {
+        match value {
+            Error::Io(e) => Self::Io(e.kind()),
+            Error::Timeout => Self::Timeout,
+            Error::Parse { field, .. } => Self::Parse { field },
+            Error::Fail(c) => Self::Fail(*c),
+            Error::JoinFailed => Self::JoinFailed,
+            Error::PanNotFound => Self::PanNotFound,
+            e => Self::Other(e.to_string()),
+        }
+    }
+}
+
+/// 再接続の試行の記録
+#[derive(Clone, Debug)]
+pub struct Reconnect {
+    pub at: SystemTime,
+
+    /// 再接続のきっかけになったイベント
+    pub cause: EventBody,
+
+    pub method: Method,
+
+    /// 失敗した場合のエラー
+    pub error: Option<ReconnectError>,
+}
+
+/// スキャンからのやり直しの間隔
+#[derive(Clone, Debug)]
+pub struct Backoff {
+    /// 最初の失敗の後に待つ時間
+    pub initial: Duration,
+
+    /// 待つ時間の上限（失敗するたびに 2 倍になる）
+    pub max: Duration,
+
+    /// 試行回数の上限（0 を指定しても 1 回は試行する）
+    pub max_attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(300),
+            max_attempts: 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub backoff: Backoff,
+
+    /// スキャンからやり直す際のチャネルマスク
+    pub channel_mask: u32,
+
+    /// スキャンからやり直す際のスキャン時間
+    pub duration: u8,
+
+    /// 保持する再接続の記録の上限（超えた分は古いものから捨てる）
+    pub history_limit: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            backoff: Backoff::default(),
+            channel_mask: 0xFFFFFFFF,
+            duration: 6,
+            history_limit: 100,
+        }
+    }
+}
+
+enum Link<Port: Transport> {
+    Joined(Session<Joined, Port>),
+    Lost(Session<Authenticated, Port>),
+}
+
+enum Received {
+    Udp(ERxUdp),
+    Event(Event),
+}
+
+/// PANA セッションを監視するスーパーバイザ
+pub struct Supervisor<Port: Transport = Box<dyn SerialPort>> {
+    link: Option<Link<Port>>,
+    pan: EPanDesc,
+    state: PanaState,
+    config: Config,
+    history: Vec<Reconnect>,
+}
+
+/// PANA セッションが失われたことを示すイベントかどうかを判定します。
+/// 切断要求の受信 (EVENT 26) の後には必ず切断の完了 (EVENT 27) が続くため、ここには含めません。
+fn is_pana_lost(body: &EventBody) -> bool {
+    matches!(
+        body,
+        EventBody::PanaError
+            | EventBody::PanaTerminated
+            | EventBody::PanaTerminationTimeout
+            | EventBody::PanaTimedOut
+    )
+}
+
+impl<Port: Transport> Supervisor<Port> {
+    pub fn new(session: Session<Joined, Port>, config: Config) -> Self {
+        Self {
+            pan: session.pan().clone(),
+            link: Some(Link::Joined(session)),
+            state: PanaState::Connected,
+            config,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> PanaState {
+        self.state
+    }
+
+    /// これまでの再接続の試行を古い順に返します。
+    /// [`Config::history_limit`] を超えた古い記録は含みません。
+    pub fn history(&self) -> &[Reconnect] {
+        &self.history
+    }
+
+    /// 接続している場合はセッションを返します。
+    pub fn session(&mut self) -> Option<&mut Session<Joined, Port>> {
+        match (self.state, &mut self.link) {
+            (PanaState::Connected, Some(Link::Joined(s))) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// 参加している PAN のコーディネータのアドレス
+    pub fn coordinator(&mut self) -> Option<Ipv6Addr> {
+        self.session().map(|s| s.coordinator())
+    }
+
+    /// 受信したイベントを状態に反映し、PANA セッションが失われていれば再接続します。
+    pub fn handle_event(&mut self, event: &Event) -> Result<()> {
+        match &event.body {
+            EventBody::PanaConnected => {
+                if let Some(Link::Joined(_)) = self.link {
+                    self.state = PanaState::Connected;
+                }
+                Ok(())
+            }
+            body if is_pana_lost(body) => self.reconnect(body.clone()),
+            _ => Ok(()),
+        }
+    }
+
+    /// UDP パケットを受信するまで待機します。
+    /// 待機中に PANA セッションが失われた場合は、再接続してから待機を続けます。
+    pub fn receive_udp(&mut self) -> Result<ERxUdp> {
+        loop {
+            let session = self.ensure_connected()?;
+            let received = session.device().raw().wait_map(|p| {
+                if p.name == ERXUDP {
+                    match ERxUdp::try_from(&p) {
+                        Ok(udp) => return WaitMap::Finish(Received::Udp(udp)),
+                        Err(e) => {
+                            warn!("Ignoring malformed ERXUDP: {e}");
+                            return WaitMap::Consume;
+                        }
+                    }
+                }
+
+                if p.name == EVENT {
+                    match Event::try_from(&p) {
+                        Ok(event) if is_pana_lost(&event.body) => {
+                            return WaitMap::Finish(Received::Event(event));
+                        }
+                        Err(e) => {
+                            warn!("Ignoring malformed EVENT: {e}");
+                            return WaitMap::Consume;
+                        }
+                        _ => {}
+                    }
+                }
+
+                WaitMap::Continue(p)
+            })?;
+
+            match received {
+                Received::Udp(udp) => return Ok(udp),
+                Received::Event(event) => self.handle_event(&event)?,
+            }
+        }
+    }
+
+    /// コーディネータに UDP パケットを暗号化して送信します。
+    /// PANA セッションが失われていた場合は、再接続してから送信します。
+    pub fn send_udp(&mut self, port: u16, data: &[u8]) -> Result<UDPSendResult> {
+        self.ensure_connected()?.send_udp(port, data)
+    }
+
+    /// PAN に参加しているセッションを返します。再接続を諦めていた場合は、再接続をやり直してから返します。
+    fn ensure_connected(&mut self) -> Result<&mut Session<Joined, Port>> {
+        if self.state != PanaState::Connected {
+            let cause = match self.history.last() {
+                Some(r) => r.cause.clone(),
+                _ => EventBody::PanaError,
+            };
+            self.reconnect(cause)?;
+        }
+
+        match &mut self.link {
+            Some(Link::Joined(s)) => Ok(s),
+            // 接続している間は、必ず PAN に参加したセッションを持っている
+            _ => unreachable!(),
+        }
+    }
+
+    /// 再認証を試み、失敗した場合はスキャンからやり直して PAN に参加し直します。
+    fn reconnect(&mut self, cause: EventBody) -> Result<()> {
+        warn!("PANA session lost: {cause:?}");
+        self.state = PanaState::Disconnected;
+
+        if let Some(Link::Joined(session)) = &mut self.link {
+            let result = session.rejoin();
+            self.record(&cause, Method::Rejoin, result.as_ref().err());
+
+            if result.is_ok() {
+                self.state = PanaState::Connected;
+                return Ok(());
+            }
+        }
+
+        let backoff = self.config.backoff.clone();
+        let mut delay = backoff.initial;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+
+            let result = self.rescan();
+            self.record(&cause, Method::Rescan, result.as_ref().err());
+
+            match result {
+                Ok(_) => {
+                    self.state = PanaState::Connected;
+                    return Ok(());
+                }
+                Err(e) if attempts >= backoff.max_attempts => return Err(e),
+                Err(_) => {}
+            }
+
+            sleep(delay);
+            delay = (delay * 2).min(backoff.max);
+        }
+    }
+
+    /// アクティブスキャンで元の PAN を探して参加し直します。
+    /// 元のコーディネータが見つからなければ、別の PAN には参加せずに [`Error::PanNotFound`] を返します。
+    fn rescan(&mut self) -> Result<()> {
+        let mut session = match self.link.take() {
+            Some(Link::Joined(s)) => s.into_authenticated(),
+            Some(Link::Lost(s)) => s,
+            _ => unreachable!(),
+        };
+
+        let pans = session.scan_active(true, self.config.channel_mask, self.config.duration, 0);
+        let pan = match pans {
+            Ok(pans) => pans.into_iter().find(|p| p.addr == self.pan.addr),
+            Err(e) => {
+                self.link = Some(Link::Lost(session));
+                return Err(e);
+            }
+        };
+
+        let pan = match pan {
+            Some(p) => p,
+            _ => {
+                self.link = Some(Link::Lost(session));
+                return Err(Error::PanNotFound);
+            }
+        };
+
+        match session.join(&pan) {
+            Ok(s) => {
+                self.pan = pan;
+                self.link = Some(Link::Joined(s));
+                Ok(())
+            }
+            Err(f) => {
+                self.link = Some(Link::Lost(f.session));
+                Err(f.error)
+            }
+        }
+    }
+
+    fn record(&mut self, cause: &EventBody, method: Method, error: Option<&Error>) {
+        match error {
+            Some(e) => warn!("Failed to reconnect ({method:?}): {e}"),
+            _ => info!("Reconnected ({method:?})"),
+        }
+
+        self.history.push(Reconnect {
+            at: SystemTime::now(),
+            cause: cause.clone(),
+            method,
+            error: error.map(ReconnectError::from),
+        });
+
+        let excess = self.history.len().saturating_sub(self.config.history_limit);
+        self.history.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Error, Session};
+    use crate::event::EventBody;
+    use crate::event::epandesc::EPanDesc;
+    use crate::mock::{MockBp35c0, test_pan};
+    use crate::supervisor::{Backoff, Config, Method, PanaState, ReconnectError, Supervisor};
+
+    const PANA_TERMINATION_REQUEST: &[u8] = b"EVENT 26 FE80:0000:0000:0000:021D:1290:0000:0002 0";
+    const PANA_TERMINATED: &[u8] = b"EVENT 27 FE80:0000:0000:0000:021D:1290:0000:0002 0";
+    const PANA_TIMED_OUT: &[u8] = b"EVENT 29 FE80:0000:0000:0000:021D:1290:0000:0002 0";
+    const ERXUDP: &[u8] = b"ERXUDP FE80:0000:0000:0000:021D:1290:0000:0002 FE80:0000:0000:0000:021D:1290:0000:0001 0E1A 0E1A 001D129000000002 1 0 0002 ABCD";
+
+    #[test]
+    fn test_reconnect() {
+        let mock = MockBp35c0::with_test_pan();
+
+        let mut session = Session::connect(mock.clone()).unwrap();
+        let pan = session
+            .scan_active(true, 0xFFFFFFFF, 6, 0)
+            .unwrap()
+            .remove(0);
+        let session = session.authenticate([0x30; 16], b"PASSWORD").unwrap();
+        let session = session.join(&pan).unwrap();
+
+        let config = Config {
+            backoff: Backoff {
+                initial: Duration::ZERO,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(session, config);
+
+        // 再認証で復帰する
+        mock.push_line(PANA_TIMED_OUT);
+        mock.push_line(ERXUDP);
+        assert_eq!(vec![0xAB, 0xCD], supervisor.receive_udp().unwrap().data);
+        assert_eq!(PanaState::Connected, supervisor.state());
+        assert_eq!(1, supervisor.history().len());
+        assert_eq!(Method::Rejoin, supervisor.history()[0].method);
+        assert!(matches!(
+            supervisor.history()[0].cause,
+            EventBody::PanaTimedOut
+        ));
+
+        // 再認証とスキャンからのやり直しに 1 回ずつ失敗してから復帰する
+        mock.push_join_result(false);
+        mock.push_join_result(false);
+        mock.push_line(PANA_TIMED_OUT);
+        mock.push_line(ERXUDP);
+        assert!(supervisor.receive_udp().is_ok());
+        assert_eq!(PanaState::Connected, supervisor.state());
+
+        let history = &supervisor.history()[1..];
+        assert_eq!(3, history.len());
+        assert_eq!(
+            vec![Method::Rejoin, Method::Rescan, Method::Rescan],
+            history.iter().map(|r| r.method).collect::<Vec<_>>(),
+        );
+        assert_eq!(Some(ReconnectError::JoinFailed), history[0].error);
+        assert_eq!(Some(ReconnectError::JoinFailed), history[1].error);
+        assert_eq!(None, history[2].error);
+    }
+
+    #[test]
+    fn test_termination_request() {
+        let pan = test_pan();
+
+        let mock = MockBp35c0::new();
+        let session = Session::connect(mock.clone()).unwrap();
+        let session = session.authenticate([0x30; 16], b"PASSWORD").unwrap();
+        let session = session.join(&pan).unwrap();
+        let mut supervisor = Supervisor::new(session, Config::default());
+
+        // 切断要求 (EVENT 26) とそれに続く切断の完了 (EVENT 27) で再接続するのは 1 回だけ
+        mock.push_line(PANA_TERMINATION_REQUEST);
+        mock.push_line(PANA_TERMINATED);
+        mock.push_line(ERXUDP);
+        assert!(supervisor.receive_udp().is_ok());
+        assert_eq!(PanaState::Connected, supervisor.state());
+        assert_eq!(1, supervisor.history().len());
+        assert_eq!(Method::Rejoin, supervisor.history()[0].method);
+        assert!(matches!(
+            supervisor.history()[0].cause,
+            EventBody::PanaTerminated
+        ));
+    }
+
+    #[test]
+    fn test_give_up() {
+        let pan = test_pan();
+
+        // スキャンでは別の PAN だけが見つかる
+        let mock = MockBp35c0::new();
+        mock.add_pan(EPanDesc {
+            pan_id: 0x5678,
+            addr: [0x00, 0x1D, 0x12, 0x90, 0x00, 0x00, 0x00, 0x03],
+            ..pan.clone()
+        });
+
+        let session = Session::connect(mock.clone()).unwrap();
+        let session = session.authenticate([0x30; 16], b"PASSWORD").unwrap();
+        let session = session.join(&pan).unwrap();
+
+        let config = Config {
+            backoff: Backoff {
+                initial: Duration::ZERO,
+                max_attempts: 2,
+                ..Default::default()
+            },
+            history_limit: 2,
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(session, config);
+
+        // 元の PAN が見つからなければ、別の PAN には参加せずに諦める
+        mock.push_join_result(false);
+        mock.push_line(PANA_TIMED_OUT);
+        assert!(matches!(supervisor.receive_udp(), Err(Error::PanNotFound)));
+        assert_eq!(PanaState::Disconnected, supervisor.state());
+        assert!(supervisor.session().is_none());
+        assert_eq!(Some(b"1234".to_vec()), mock.register(b"S03"));
+
+        // 再認証 1 回とスキャンからのやり直し 2 回のうち、新しい 2 件だけを残す
+        assert_eq!(
+            vec![Method::Rescan, Method::Rescan],
+            supervisor
+                .history()
+                .iter()
+                .map(|r| r.method)
+                .collect::<Vec<_>>(),
+        );
+
+        // 元の PAN が見つかるようになれば、次の送信で再接続する
+        mock.add_pan(pan);
+        assert!(supervisor.send_udp(0x0E1A, b"a").is_ok());
+        assert_eq!(PanaState::Connected, supervisor.state());
+    }
+}
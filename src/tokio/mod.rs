@@ -0,0 +1,357 @@
+//! tokio を用いた非同期版のドライバ
+//!
+//! コマンドの送信内容や応答の解釈は同期版の [`crate::Bp35c0`] と共通で、待機する部分だけが非同期になっています。
+//!
+//! PAN への参加や UDP の送受信は、同期版と同じく [`Session`] を通して行います。
+
+use std::io::ErrorKind;
+use std::net::Ipv6Addr;
+use std::time::{Duration, Instant};
+
+use ::tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use ::tokio::time::timeout_at;
+
+use crate::{Error, Result};
+use crate::event::Event;
+use crate::event::erxudp::ERxUdp;
+use crate::framer::Framer;
+use crate::protocol::{commands, Io, Protocol};
+use crate::session::{session, Connected, Joined};
+
+pub mod raw;
+
+/// 非同期版の BP35C0 ドライバ
+pub struct Bp35c0<Port = tokio_serial::SerialStream> {
+    pub(crate) core: Protocol<TokioIo<Port>>,
+}
+
+/// 非同期版のドライバのセッション
+pub type Session<State, Port = tokio_serial::SerialStream> =
+    crate::session::Session<State, Bp35c0<Port>>;
+
+/// 非同期版のドライバの通信路
+pub(crate) struct TokioIo<Port> {
+    reader: BufReader<Port>,
+}
+
+impl<Port> Io for TokioIo<Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    type Instant = Instant;
+
+    fn deadline(&self, timeout: Duration) -> Option<Instant> {
+        Some(Instant::now() + timeout)
+    }
+
+    async fn fill(&mut self, framer: &mut Framer, deadline: Option<Instant>) -> Result<()> {
+        // fill_buf は中断されても何も消費しないので、次回の読み込みで続きから再開できる
+        let read = self.reader.fill_buf();
+        let bytes = match deadline {
+            Some(d) => timeout_at(d.into(), read)
+                .await
+                .map_err(|_| Error::Timeout)??,
+            _ => read.await?,
+        };
+
+        if bytes.is_empty() {
+            return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+
+        let len = bytes.len();
+        framer.push(bytes);
+        self.reader.consume(len);
+        Ok(())
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.reader.get_mut().write_all(bytes).await?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        self.reader.get_mut().flush().await?;
+        Ok(())
+    }
+}
+
+/// [`commands`] の一覧から、応答を非同期に待つ公開メソッドを生成します。
+macro_rules! nonblocking {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            $vis async fn $name(&mut self, $($arg: $ty),*) -> Result<$ret> {
+                self.core.$name($($arg),*).await
+            }
+        )*
+    };
+}
+
+impl<Port> Bp35c0<Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    pub async fn connect(port: Port) -> Result<Self> {
+        let io = TokioIo {
+            reader: BufReader::new(port),
+        };
+
+        Ok(Self {
+            core: Protocol::connect(io).await?,
+        })
+    }
+
+    commands!(nonblocking);
+
+    pub async fn wait_for_event<F>(&mut self, criteria: F) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        self.wait_for_event_until(None, criteria).await
+    }
+
+    pub async fn wait_for_event_until<F>(
+        &mut self,
+        deadline: Option<Instant>,
+        criteria: F,
+    ) -> Result<Event>
+    where
+        F: Fn(&Event) -> bool,
+    {
+        self.core.wait_for_event_until(deadline, criteria).await
+    }
+
+    pub async fn wait_for_udp<F>(&mut self, criteria: F) -> Result<ERxUdp>
+    where
+        F: Fn(&ERxUdp) -> bool,
+    {
+        self.core.wait_for_udp(criteria).await
+    }
+
+    /// 指定したアドレスに ICMP Echo request を送信し、Echo reply (EPONG) を受信するまでの時間を返します。
+    /// [`crate::cmd::skping::TIMEOUT`] 以内に応答が無い場合は [`Error::Timeout`] を返します。
+    pub async fn ping(&mut self, ip_addr: Ipv6Addr, side: u8) -> Result<Duration> {
+        let start = Instant::now();
+        self.core.ping(ip_addr, side).await?;
+        Ok(start.elapsed())
+    }
+}
+
+session!(nonblocking, impl<Port> Bp35c0<Port> where Port: AsyncRead + AsyncWrite + Unpin);
+
+impl<Port> Session<Connected, Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    pub async fn connect(port: Port) -> Result<Self> {
+        Ok(Self::from(Bp35c0::connect(port).await?))
+    }
+}
+
+impl<Port> Session<Joined, Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    /// コーディネータとの往復時間を計測します。
+    pub async fn ping(&mut self) -> Result<Duration> {
+        let (coordinator, side) = (self.coordinator(), self.pan().side);
+        self.device().ping(coordinator, side).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+    use std::time::Duration;
+
+    use ::tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream, duplex};
+    use ::tokio::time::{Instant, sleep};
+
+    use crate::{Error, FailCode, Split};
+    use crate::cmd::{skjoin, sksendto, sksreg};
+    use crate::event::UDPSendResult;
+    use crate::mock::test_pan;
+    use crate::session::Joined;
+    use crate::snapshot::Snapshot;
+    use crate::tokio::{Bp35c0, Session};
+
+    fn assert_send<T: Send>(_: T) {}
+
+    /// `tokio::spawn` に渡せるように、各メソッドの Future は `Send` でなければならない
+    #[allow(dead_code)]
+    fn assert_futures_send(device: &mut Bp35c0<DuplexStream>) {
+        assert_send(device.version());
+        assert_send(device.scan_active(false, 0xFFFFFFFF, 6, 0));
+        assert_send(device.join(Ipv6Addr::LOCALHOST));
+        assert_send(device.send_to(
+            1,
+            Ipv6Addr::LOCALHOST,
+            0x0E1A,
+            sksendto::Security::Encrypted,
+            0,
+            b"",
+        ));
+        assert_send(device.receive_udp());
+        assert_send(device.restore_registers(&Snapshot::default()));
+        assert_send(device.ping(Ipv6Addr::LOCALHOST, 0));
+        assert_send(device.wait_for_event(|_| true));
+        assert_send(device.raw().wait_for_ok());
+    }
+
+    #[allow(dead_code)]
+    fn assert_session_futures_send(session: &mut Session<Joined, DuplexStream>) {
+        assert_send(session.send_udp(0x0E1A, b""));
+        assert_send(session.receive_udp());
+        assert_send(session.ping());
+    }
+
+    #[::tokio::test]
+    async fn test_session() {
+        // SKRESET, SKSREG, SKSETRBID, SKSETPWD, SKSREG S02, SKSREG S03, SKLL64, SKJOIN, SKSENDTO への応答
+        const RX: &[u8] = b"OK\r\nOK\r\nOK\r\nOK\r\nOK\r\nOK\r\nFE80:0000:0000:0000:021D:1290:0000:0002\r\nOK\r\nEVENT 25 FE80:0000:0000:0000:021D:1290:0000:0002 0\r\nEVENT 21 FE80:0000:0000:0000:021D:1290:0000:0002 0 00\r\nOK\r\n";
+        const SKSENDTO: &[u8] =
+            b"SKSENDTO 1 FE80:0000:0000:0000:021D:1290:0000:0002 0E1A 1 0 0001 a\r\n";
+
+        let (port, mut module) = duplex(1024);
+        module.write_all(RX).await.unwrap();
+
+        let pan = test_pan();
+
+        // UDP の送信は PAN に参加したセッションからのみ行える
+        let session = Session::connect(port).await.unwrap();
+        let session = session.authenticate([0x30; 16], b"PASSWORD").await.unwrap();
+        let mut session = session.join(&pan).await.unwrap();
+        assert_eq!("fe80::21d:1290:0:2".parse(), Ok(session.coordinator()));
+        assert!(matches!(
+            session.send_udp(0x0E1A, b"a").await,
+            Ok(UDPSendResult::Success)
+        ));
+        drop(session);
+
+        let mut sent = Vec::new();
+        module.read_to_end(&mut sent).await.unwrap();
+        assert!(sent.ends_with(SKSENDTO));
+    }
+
+    #[::tokio::test]
+    async fn test_connect_and_version() {
+        let (port, mut module) = duplex(1024);
+
+        // SKRESET, SKSREG, SKVER への応答
+        module
+            .write_all(b"OK\r\nOK\r\nEVER 1.2.10\r\nOK\r\n")
+            .await
+            .unwrap();
+
+        let mut device = Bp35c0::connect(port).await.unwrap();
+        assert_eq!("1.2.10", device.version().await.unwrap().version);
+        drop(device);
+
+        let mut sent = Vec::new();
+        module.read_to_end(&mut sent).await.unwrap();
+        assert_eq!(b"\r\nSKRESET\r\nSKSREG SFE 0\r\nSKVER\r\n", sent.as_slice());
+    }
+
+    #[::tokio::test(start_paused = true)]
+    async fn test_join_deadline() {
+        let (port, mut module) = duplex(1024);
+        module.write_all(b"OK\r\nOK\r\n").await.unwrap();
+
+        let mut device = Bp35c0::connect(port).await.unwrap();
+
+        // OK が届くまでの時間も SKJOIN の待機時間に含める
+        let start = Instant::now();
+        let responder = ::tokio::spawn(async move {
+            sleep(Duration::from_secs(40)).await;
+            module.write_all(b"OK\r\n").await.unwrap();
+            module
+        });
+
+        let result = device.join(Ipv6Addr::LOCALHOST).await;
+        assert!(matches!(result, Err(Error::Timeout)));
+        assert!(start.elapsed() < skjoin::TIMEOUT + Duration::from_secs(1));
+
+        drop(responder.await.unwrap());
+    }
+
+    #[::tokio::test(start_paused = true)]
+    async fn test_send_to_fail() {
+        let (port, mut module) = duplex(1024);
+        module
+            .write_all(b"OK\r\nOK\r\nFAIL ER06\r\n")
+            .await
+            .unwrap();
+
+        let mut device = Bp35c0::connect(port).await.unwrap();
+
+        // FAIL の場合は EVENT 21 を待たずにエラーを返す
+        let start = Instant::now();
+        let result = device
+            .send_to(
+                1,
+                Ipv6Addr::LOCALHOST,
+                0x0E1A,
+                sksendto::Security::Encrypted,
+                0,
+                b"a",
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Fail(FailCode::ArgumentOutOfRange))
+        ));
+        assert!(start.elapsed() < sksendto::TIMEOUT);
+    }
+
+    #[::tokio::test(start_paused = true)]
+    async fn test_scan_energy() {
+        let (port, mut module) = duplex(1024);
+
+        // SKRESET, SKSREG, SKSCAN への応答（EVENT 1F が EEDSCAN より先に届く場合）
+        module
+            .write_all(b"OK\r\nOK\r\nOK\r\nEVENT 1F FE80:0000:0000:0000:021D:1290:1234:5678 0\r\nEEDSCAN\r\n21 3C 22 42\r\n")
+            .await
+            .unwrap();
+
+        let mut device = Bp35c0::connect(port).await.unwrap();
+        let levels = device.scan_energy(0x3, 4, 0).await.unwrap();
+        assert_eq!(2, levels.len());
+        assert_eq!(0x42, levels[1].rssi);
+    }
+
+    #[::tokio::test]
+    async fn test_parity() {
+        // SKRESET, SKSREG, SKVER, SKSREG S02 3B, SKLL64, SKSREG S02, SKSAVE への応答
+        const RX: &[u8] = b"OK\r\nOK\r\nEVER 1.2.10\r\nOK\r\nOK\r\nFE80:0000:0000:0000:021D:1290:1234:5678\r\nESREG 3B\r\nOK\r\nOK\r\n";
+        const ADDR: [u8; 8] = [0x00, 0x1D, 0x12, 0x90, 0x12, 0x34, 0x56, 0x78];
+
+        // 同期版と非同期版は同じ応答に対して同じコマンドを送信し、同じ結果を返す
+        let mut device = crate::Bp35c0::connect(Split::new(RX, Vec::new())).unwrap();
+        let version = device.version().unwrap().version;
+        device
+            .set_channel(sksreg::Channel::new(0x3B).unwrap())
+            .unwrap();
+        let ip_addr = device.mac_to_ip_addr(ADDR).unwrap().ip_addr;
+        let channel = device.channel().unwrap();
+        device.save_config().unwrap();
+        let expected = (version, ip_addr, channel, device.port_mut().writer.clone());
+
+        let (port, mut module) = duplex(1024);
+        module.write_all(RX).await.unwrap();
+
+        let mut device = Bp35c0::connect(port).await.unwrap();
+        let version = device.version().await.unwrap().version;
+        device
+            .set_channel(sksreg::Channel::new(0x3B).unwrap())
+            .await
+            .unwrap();
+        let ip_addr = device.mac_to_ip_addr(ADDR).await.unwrap().ip_addr;
+        let channel = device.channel().await.unwrap();
+        device.save_config().await.unwrap();
+        drop(device);
+
+        let mut sent = Vec::new();
+        module.read_to_end(&mut sent).await.unwrap();
+        assert_eq!(expected, (version, ip_addr, channel, sent));
+    }
+}
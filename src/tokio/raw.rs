@@ -0,0 +1,193 @@
+//! プロトコルを直接扱う非同期版の低レベルな API
+//!
+//! 同期版の [`crate::raw`] と同じく、使い方によっては [`Bp35c0`] の高レベルな API が前提としている状態と食い違うことがあります。
+
+use std::net::Ipv6Addr;
+use std::time::Instant;
+
+use ::tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::{Result, WaitMap};
+use crate::cmd::{sksreg, Encode, Response};
+use crate::event::eedscan::EnergyLevel;
+use crate::event::epandesc::EPanDesc;
+use crate::payload::Payload;
+use crate::tokio::Bp35c0;
+
+/// [`Bp35c0::raw`] で得られる低レベルなハンドル
+pub struct Raw<'a, Port> {
+    device: &'a mut Bp35c0<Port>,
+}
+
+impl<Port> Bp35c0<Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    /// プロトコルを直接扱う低レベルな API を返します。
+    pub fn raw(&mut self) -> Raw<'_, Port> {
+        Raw { device: self }
+    }
+}
+
+impl<Port> Raw<'_, Port>
+where
+    Port: AsyncRead + AsyncWrite + Unpin,
+{
+    pub async fn receive_payload(&mut self) -> Result<Payload> {
+        self.receive_payload_until(None).await
+    }
+
+    pub async fn receive_payload_until(&mut self, deadline: Option<Instant>) -> Result<Payload> {
+        self.device.core.receive_payload_until(deadline).await
+    }
+
+    pub async fn receive_payload_unbuffered(&mut self) -> Result<Payload> {
+        self.receive_payload_unbuffered_until(None).await
+    }
+
+    pub async fn receive_payload_unbuffered_until(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Payload> {
+        self.device
+            .core
+            .receive_payload_unbuffered_until(deadline)
+            .await
+    }
+
+    pub async fn send_payload(&mut self, payload: &Payload) -> Result<()> {
+        self.device.core.send_payload(payload).await
+    }
+
+    pub async fn send<E>(&mut self, input: &E) -> Result<()>
+    where
+        E: Encode,
+    {
+        self.device.core.send(input).await
+    }
+
+    pub async fn wait_map<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: FnMut(Payload) -> WaitMap<T>,
+    {
+        self.wait_map_until(None, f).await
+    }
+
+    pub async fn wait_map_until<F, T>(&mut self, deadline: Option<Instant>, f: F) -> Result<T>
+    where
+        F: FnMut(Payload) -> WaitMap<T>,
+    {
+        self.device.core.wait_map_until(deadline, f).await
+    }
+
+    pub async fn wait_for<F>(&mut self, criteria: F) -> Result<Payload>
+    where
+        F: Fn(&Payload) -> bool,
+    {
+        self.wait_for_until(None, criteria).await
+    }
+
+    pub async fn wait_for_until<F>(
+        &mut self,
+        deadline: Option<Instant>,
+        criteria: F,
+    ) -> Result<Payload>
+    where
+        F: Fn(&Payload) -> bool,
+    {
+        self.device.core.wait_for_until(deadline, criteria).await
+    }
+
+    /// `OK` を受信するまで待機します。
+    /// 先に `FAIL ERxx` を受信した場合は [`crate::Error::Fail`] を返します。
+    pub async fn wait_for_ok(&mut self) -> Result<()> {
+        self.wait_for_ok_until(None).await
+    }
+
+    pub async fn wait_for_ok_until(&mut self, deadline: Option<Instant>) -> Result<()> {
+        self.device.core.wait_for_ok_until(deadline).await
+    }
+
+    pub async fn wait_for_response<R>(&mut self) -> Result<R>
+    where
+        R: Response,
+    {
+        self.wait_for_response_until(None).await
+    }
+
+    pub async fn wait_for_response_until<R>(&mut self, deadline: Option<Instant>) -> Result<R>
+    where
+        R: Response,
+    {
+        self.device.core.wait_for_response_until(deadline).await
+    }
+
+    pub async fn reset(&mut self) -> Result<()> {
+        self.device.core.reset().await
+    }
+
+    pub async fn read_register(&mut self, register: sksreg::Register) -> Result<sksreg::Output> {
+        self.device.core.read_register(register).await
+    }
+
+    pub async fn set_register(
+        &mut self,
+        register: sksreg::Register,
+        value: sksreg::Value,
+    ) -> Result<()> {
+        self.device.core.set_register(register, value).await
+    }
+
+    pub async fn join_nowait(&mut self, ip_addr: Ipv6Addr) -> Result<()> {
+        self.device.core.join_nowait(ip_addr).await
+    }
+
+    pub async fn scan_active_nowait(
+        &mut self,
+        ie: bool,
+        channel_mask: u32,
+        duration: u8,
+        side: u8,
+    ) -> Result<()> {
+        self.device
+            .core
+            .scan_active_nowait(ie, channel_mask, duration, side)
+            .await
+    }
+
+    pub async fn scan_energy_nowait(
+        &mut self,
+        channel_mask: u32,
+        duration: u8,
+        side: u8,
+    ) -> Result<()> {
+        self.device
+            .core
+            .scan_energy_nowait(channel_mask, duration, side)
+            .await
+    }
+
+    /// EEDSCAN に続いて出力される `<CHANNEL> <RSSI> ...` の行を読み込みます。
+    pub async fn receive_eedscan(&mut self, payload: &Payload) -> Result<Vec<EnergyLevel>> {
+        self.receive_eedscan_until(None, payload).await
+    }
+
+    pub async fn receive_eedscan_until(
+        &mut self,
+        deadline: Option<Instant>,
+        payload: &Payload,
+    ) -> Result<Vec<EnergyLevel>> {
+        self.device
+            .core
+            .receive_eedscan_until(deadline, payload)
+            .await
+    }
+
+    pub async fn receive_epandesc(&mut self) -> Result<EPanDesc> {
+        self.receive_epandesc_until(None).await
+    }
+
+    pub async fn receive_epandesc_until(&mut self, deadline: Option<Instant>) -> Result<EPanDesc> {
+        self.device.core.receive_epandesc_until(deadline).await
+    }
+}
@@ -0,0 +1,264 @@
+//! 通信内容の記録と再生
+//!
+//! [`Recorder`] は通信路を包み、読み書きしたバイト列をすべて時刻付きで記録します。
+//! 記録は 1 行に 1 回の読み書きを `<経過時間 (μs)> <方向> <16 進数のバイト列>` の形式で書き出したもので、
+//! 方向は送信が `>`、受信が `<` です。`#` で始まる行はコメントとして扱います。
+//!
+//! [`Replay`] は記録を読み込んで通信路として振る舞い、記録されたとおりの受信データを返します。
+//! 送信されたデータは記録と照合し、食い違えばエラーにするので、現場で起きた問題をテストで再現できます。
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const SENT: &str = ">";
+const RECEIVED: &str = "<";
+
+/// 読み書きを記録する通信路
+pub struct Recorder<T, W> {
+    inner: T,
+    sink: W,
+    start: Instant,
+}
+
+impl<T> Recorder<T, File> {
+    /// 記録先のファイルを作成して記録を始めます。
+    pub fn create<P>(inner: T, path: P) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self::new(inner, File::create(path)?))
+    }
+}
+
+impl<T, W> Recorder<T, W>
+where
+    W: Write,
+{
+    pub fn new(inner: T, sink: W) -> Self {
+        Self {
+            inner,
+            sink,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn into_inner(self) -> (T, W) {
+        (self.inner, self.sink)
+    }
+
+    fn record(&mut self, direction: &str, bytes: &[u8]) -> std::io::Result<()> {
+        let elapsed = self.start.elapsed().as_micros();
+
+        writeln!(self.sink, "{elapsed} {direction} {}", hex::encode(bytes))?;
+        self.sink.flush()
+    }
+}
+
+impl<T, W> Read for Recorder<T, W>
+where
+    T: Read,
+    W: Write,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if len > 0 {
+            self.record(RECEIVED, &buf[..len])?;
+        }
+
+        Ok(len)
+    }
+}
+
+impl<T, W> Write for Recorder<T, W>
+where
+    T: Write,
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        if len > 0 {
+            self.record(SENT, &buf[..len])?;
+        }
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+enum Entry {
+    Sent(VecDeque<u8>),
+    Received(VecDeque<u8>),
+}
+
+/// 記録を再生する通信路
+///
+/// 記録の時刻は無視し、読み書きの順序だけを再現します。
+/// 受信データは、それより前に記録されている送信データがすべて書き込まれるまで返しません（読み込みタイムアウトとして扱います）。
+pub struct Replay {
+    entries: VecDeque<Entry>,
+}
+
+impl Replay {
+    pub fn open<P>(path: P) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R>(reader: R) -> std::io::Result<Self>
+    where
+        R: BufRead,
+    {
+        let mut entries = VecDeque::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split(' ');
+            let (_, direction, bytes) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(t), Some(d), Some(b)) => (t, d, b),
+                (Some(t), Some(d), None) => (t, d, ""),
+                _ => return Err(invalid_line(line)),
+            };
+
+            let bytes = hex::decode(bytes).map_err(|_| invalid_line(line))?;
+            if bytes.is_empty() {
+                continue;
+            }
+
+            entries.push_back(match direction {
+                SENT => Entry::Sent(bytes.into()),
+                RECEIVED => Entry::Received(bytes.into()),
+                _ => return Err(invalid_line(line)),
+            });
+        }
+
+        Ok(Self { entries })
+    }
+
+    /// 記録をすべて再生し終えたかどうか
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+fn invalid_line(line: &str) -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid transcript line: {line:?}"),
+    )
+}
+
+impl Read for Replay {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes = match self.entries.front_mut() {
+            Some(Entry::Received(bytes)) => bytes,
+            Some(Entry::Sent(_)) => return Err(ErrorKind::TimedOut.into()),
+            _ => return Ok(0),
+        };
+
+        let len = bytes.read(buf)?;
+        if bytes.is_empty() {
+            self.entries.pop_front();
+        }
+
+        Ok(len)
+    }
+}
+
+impl Write for Replay {
+    /// 記録と一致する先頭部分だけを書き込みます。
+    /// 先頭の 1 バイトから食い違う場合は何も消費せずに [`ErrorKind::InvalidData`] を返すので、
+    /// 食い違いが起きた後も記録の位置は変わりません。
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for (i, b) in buf.iter().enumerate() {
+            let expected = match self.entries.front_mut() {
+                Some(Entry::Sent(bytes)) if bytes.front() == Some(b) => bytes,
+                _ if i > 0 => return Ok(i),
+                Some(Entry::Sent(_)) => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("write mismatch: {:?}", String::from_utf8_lossy(buf)),
+                    ));
+                }
+                _ => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unexpected write: {:?}", String::from_utf8_lossy(buf)),
+                    ));
+                }
+            };
+
+            expected.pop_front();
+            if expected.is_empty() {
+                self.entries.pop_front();
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{ErrorKind, Write};
+
+    use crate::{Bp35c0, Error};
+    use crate::mock::MockBp35c0;
+    use crate::transcript::{Recorder, Replay};
+
+    #[test]
+    fn test_record_and_replay() {
+        let mock = MockBp35c0::new();
+        mock.set_version("1.2.10");
+
+        let mut transcript = Vec::new();
+        {
+            let mut device = Bp35c0::connect(Recorder::new(mock, &mut transcript)).unwrap();
+            assert_eq!("1.2.10", device.version().unwrap().version);
+        }
+
+        let lines = String::from_utf8(transcript.clone()).unwrap();
+        let skver = format!("> {}", hex::encode("SKVER"));
+        assert!(lines.lines().any(|l| l.ends_with(&skver)));
+
+        let mut replay = Replay::from_reader(transcript.as_slice()).unwrap();
+        {
+            let mut device = Bp35c0::connect(&mut replay).unwrap();
+            assert_eq!("1.2.10", device.version().unwrap().version);
+            assert!(matches!(
+                device.info(),
+                Err(Error::Io(e)) if e.kind() == ErrorKind::InvalidData
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_mismatch() {
+        let transcript = format!("0 > {}\n", hex::encode("SKVER\r\n"));
+        let mut replay = Replay::from_reader(transcript.as_bytes()).unwrap();
+
+        // 一致する先頭部分だけを書き込み、食い違っても記録の位置は進まない
+        assert_eq!(2, replay.write(b"SKINFO\r\n").unwrap());
+        let e = replay.write(b"INFO\r\n").unwrap_err();
+        assert_eq!(ErrorKind::InvalidData, e.kind());
+        assert_eq!(5, replay.write(b"VER\r\nSKINFO").unwrap());
+        assert!(replay.is_finished());
+        assert!(replay.write(b"SKINFO").is_err());
+    }
+}
@@ -0,0 +1,43 @@
+use std::io::{Read, Write};
+
+/// BP35C0 との通信路
+///
+/// 読み書きができるものであれば何でも使えます（シリアルポート、TCP 経由のシリアルブリッジ、pty、テスト用のモックなど）。
+pub trait Transport: Read + Write {}
+
+impl<T> Transport for T where T: Read + Write {}
+
+/// 読み込み側と書き込み側が別々のオブジェクトになっている通信路をまとめます。
+#[derive(Debug)]
+pub struct Split<R, W> {
+    pub reader: R,
+    pub writer: W,
+}
+
+impl<R, W> Split<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer }
+    }
+}
+
+impl<R, W> Read for Split<R, W>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+impl<R, W> Write for Split<R, W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
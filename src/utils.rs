@@ -1,5 +1,13 @@
+use alloc::vec::Vec;
+use core::net::Ipv6Addr;
+use core::str::FromStr;
+
+use bstr::BString;
 use byteorder::{BigEndian, ByteOrder};
 
+use crate::{Error, Result};
+use crate::payload::Payload;
+
 #[inline]
 fn atoi(b: u8) -> u8 {
     if b <= b'9' {
@@ -26,6 +34,58 @@ pub(crate) fn parse_hex_bytes(src: &[u8]) -> Vec<u8> {
         .collect()
 }
 
+/// 16 進文字列をバイト列に変換します。16 進数として不正な場合は [`Error::Parse`] を返します。
+pub(crate) fn try_parse_hex_bytes(field: &'static str, src: &[u8]) -> Result<Vec<u8>> {
+    if !src.len().is_multiple_of(2) || !src.iter().all(u8::is_ascii_hexdigit) {
+        return Err(Error::parse(field, src));
+    }
+
+    Ok(parse_hex_bytes(&src.to_ascii_uppercase()))
+}
+
+pub(crate) fn parse_hex_array<const N: usize>(field: &'static str, src: &[u8]) -> Result<[u8; N]> {
+    try_parse_hex_bytes(field, src)?
+        .try_into()
+        .map_err(|_| Error::parse(field, src))
+}
+
+pub(crate) fn parse_hex_u8(field: &'static str, src: &[u8]) -> Result<u8> {
+    parse_hex_array::<1>(field, src).map(|b| b[0])
+}
+
+pub(crate) fn parse_hex_u16(field: &'static str, src: &[u8]) -> Result<u16> {
+    parse_hex_array::<2>(field, src).map(|b| BigEndian::read_u16(&b))
+}
+
+pub(crate) fn parse_hex_u32(field: &'static str, src: &[u8]) -> Result<u32> {
+    parse_hex_array::<4>(field, src).map(|b| BigEndian::read_u32(&b))
+}
+
+/// `0` または `1` の 1 文字からなる値を変換します。
+pub(crate) fn parse_flag(field: &'static str, src: &[u8]) -> Result<bool> {
+    match src {
+        b"0" => Ok(false),
+        b"1" => Ok(true),
+        _ => Err(Error::parse(field, src)),
+    }
+}
+
+pub(crate) fn parse_ipv6(field: &'static str, src: &[u8]) -> Result<Ipv6Addr> {
+    core::str::from_utf8(src)
+        .ok()
+        .and_then(|s| Ipv6Addr::from_str(s).ok())
+        .ok_or_else(|| Error::parse(field, src))
+}
+
+/// ペイロードの `index` 番目の引数を取り出します。存在しない場合は [`Error::Parse`] を返します。
+pub(crate) fn arg<'a>(payload: &'a Payload, index: usize, field: &'static str) -> Result<&'a [u8]> {
+    payload
+        .args
+        .get(index)
+        .map(Vec::as_slice)
+        .ok_or_else(|| Error::parse(field, &Vec::<u8>::from(payload)))
+}
+
 pub(crate) fn to_hex_bytes(src: &[u8]) -> Vec<u8> {
     src.iter().flat_map(|v| itoa(*v)).collect()
 }
@@ -58,9 +118,17 @@ pub(crate) fn u32_to_hex_bytes(u: u32) -> [u8; 8] {
     dst
 }
 
+/// IPv6 アドレスをモジュールが要求する省略なしの形式 (XXXX:XXXX:...:XXXX) に変換します。
+pub(crate) fn ipv6_to_hex_bytes(addr: &Ipv6Addr) -> Vec<u8> {
+    bstr::join(
+        b":",
+        addr.segments().map(u16_to_hex_bytes).map(BString::from),
+    )
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::utils::parse_hex_bytes;
+    use crate::utils::{parse_flag, parse_hex_bytes, parse_hex_u16, parse_ipv6};
 
     #[test]
     fn test_parse_hex_bytes() {
@@ -69,4 +137,14 @@ mod tests {
             parse_hex_bytes(b"0123456789ABCDEF"),
         );
     }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(0xFFFE, parse_hex_u16("pan_id", b"FFFE").unwrap());
+        assert!(parse_hex_u16("pan_id", b"FFF").is_err());
+        assert!(parse_hex_u16("pan_id", b"FFXE").is_err());
+        assert!(parse_flag("side", b"2").is_err());
+        assert!(parse_ipv6("sender", b"FE80::1").is_ok());
+        assert!(parse_ipv6("sender", b"\xFF").is_err());
+    }
 }